@@ -0,0 +1,212 @@
+use std::{
+    collections::BTreeMap,
+    fs::{read_to_string, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{convert::Conversion, data::ArtworksData, dupes::Dhash};
+
+/// An archive shared by the tasks of a [`crate::job::JobManager`].
+pub type SharedArchive = Arc<Mutex<Archive>>;
+
+/// Artworks that were downloaded completely.
+///
+/// Stored as JSON lines, every update appends the whole entry and the last line of an
+/// artwork wins, so the file can be appended to from a crashing process.
+#[derive(Debug, Default)]
+pub struct Archive {
+    path: PathBuf,
+    entries: BTreeMap<usize, ArchiveEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ArchiveEntry {
+    pub illust_id: usize,
+    pub title: String,
+    pub user_id: usize,
+    pub user_name: String,
+    pub tags: Vec<String>,
+    /// `0` all ages, `1` R-18, `2` R-18G
+    pub x_restrict: u8,
+    /// `2` when the artwork is marked as AI-generated
+    pub ai_type: u8,
+    /// position in the ranking the artwork was downloaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<usize>,
+    /// unix timestamp in seconds
+    pub downloaded_at: u64,
+    pub files: Vec<ArchiveFile>,
+    /// `.cbz` files the pages were packed into
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ArchiveFile {
+    pub page: usize,
+    pub url: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// hash of the downloaded file, also after a conversion
+    pub sha256: String,
+    /// set when duplicate detection is on and the file is an image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhash: Option<Dhash>,
+    /// artwork with a near-duplicate image, the file was removed if duplicates are skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<usize>,
+    /// set when the download was converted, `path` and `bytes` are of the converted image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversion: Option<Conversion>,
+}
+
+impl Archive {
+    /// Reads the archive at `path`, a missing file is an empty archive.
+    ///
+    /// Lines that can't be parsed, e.g. a line cut off by a crash, are skipped.
+    pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+        let content = match read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let entries = content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<ArchiveEntry>(line).ok())
+            .map(|entry| (entry.illust_id, entry))
+            .collect();
+
+        Ok(Self { path, entries })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn contains(&self, illust_id: usize) -> bool {
+        self.entries.contains_key(&illust_id)
+    }
+
+    pub fn get(&self, illust_id: usize) -> Option<&ArchiveEntry> {
+        self.entries.get(&illust_id)
+    }
+
+    /// Entries ordered by artwork id.
+    pub fn entries(&self) -> impl Iterator<Item = &ArchiveEntry> {
+        self.entries.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds or replaces the entry and appends it to the file.
+    pub fn record(&mut self, entry: ArchiveEntry) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        self.entries.insert(entry.illust_id, entry);
+
+        Ok(())
+    }
+}
+
+impl ArchiveEntry {
+    pub fn new(data: &ArtworksData, files: Vec<ArchiveFile>) -> Self {
+        Self {
+            illust_id: data.id,
+            title: data.title.clone(),
+            user_id: data.user_id,
+            user_name: data.user_name.clone(),
+            tags: data.tags.tags.iter().map(|tag| tag.tag.clone()).collect(),
+            x_restrict: data.x_restrict,
+            ai_type: data.ai_type,
+            rank: None,
+            downloaded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or(0),
+            files,
+            packages: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env::temp_dir, fs::remove_file, io::Write};
+
+    use super::{Archive, ArchiveEntry, ArchiveFile};
+
+    #[test]
+    fn test_archive() {
+        let path = temp_dir().join(format!("x-pixiv-archive-{}.jsonl", std::process::id()));
+        let entry = ArchiveEntry {
+            illust_id: 106465672,
+            title: "title".to_string(),
+            user_id: 1,
+            user_name: "user".to_string(),
+            tags: vec!["オリジナル".to_string()],
+            x_restrict: 0,
+            ai_type: 1,
+            rank: None,
+            downloaded_at: 0,
+            files: vec![ArchiveFile {
+                page: 0,
+                url: "https://i.pximg.net/img-original/img/106465672_p0.png".to_string(),
+                path: "./title-0.png".into(),
+                bytes: 10,
+                sha256: "00".to_string(),
+                dhash: None,
+                duplicate_of: None,
+                conversion: None,
+            }],
+            packages: vec![],
+        };
+        remove_file(&path).ok();
+
+        let mut archive = Archive::open(&path).unwrap();
+        assert!(archive.is_empty());
+        archive.record(entry.clone()).unwrap();
+        archive
+            .record(ArchiveEntry {
+                illust_id: 2,
+                ..entry.clone()
+            })
+            .unwrap();
+        archive
+            .record(ArchiveEntry {
+                title: "renamed".to_string(),
+                ..entry.clone()
+            })
+            .unwrap();
+        // a line cut off by a crash
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"illust_id\":3,")
+            .unwrap();
+
+        let archive = Archive::open(&path).unwrap();
+        remove_file(&path).ok();
+
+        assert_eq!(archive.len(), 2);
+        assert!(archive.contains(2));
+        assert!(!archive.contains(3));
+        assert_eq!(archive.get(106465672).unwrap().title, "renamed");
+    }
+}
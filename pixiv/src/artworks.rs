@@ -1,11 +1,57 @@
 use scraper::{Html, Selector};
 
+use crate::client;
 use crate::data::{Api, ArtworkPagesData, ArtworksData, Illust};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+/// Image size downloaded for every page.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Quality {
+    #[default]
+    Original,
+    /// at most 1200px, always jpg
+    Regular,
+    /// at most 540px, always jpg
+    Small,
+}
+
+impl Quality {
+    /// Key of the size in [`ArtworksData::page_urls`].
+    pub fn key(&self) -> &'static str {
+        match self {
+            Quality::Original => "original",
+            Quality::Regular => "regular",
+            Quality::Small => "small",
+        }
+    }
+}
+
+impl Display for Quality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
+
+impl FromStr for Quality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "original" => Ok(Quality::Original),
+            "regular" => Ok(Quality::Regular),
+            "small" => Ok(Quality::Small),
+            _ => Err(format!("unknown quality: {}", s)),
+        }
+    }
+}
 
 pub async fn get_artworks_data(id: usize) -> reqwest::Result<ArtworksData> {
-    let mut images = get_artworks_image_data(id).await?;
-    let html = reqwest::get(format!("https://www.pixiv.net/artworks/{}", id))
+    let page_urls = get_artworks_page_urls(id).await?;
+    let html = client::api()
+        .get(format!("https://www.pixiv.net/artworks/{}", id))
+        .send()
         .await?
         .error_for_status()?
         .text()
@@ -17,25 +63,37 @@ pub async fn get_artworks_data(id: usize) -> reqwest::Result<ArtworksData> {
     let data: Illust<HashMap<String, ArtworksData>> = serde_json::from_str(json_str).unwrap();
 
     let mut artworks_data = data.illust.get(&id.to_string()).unwrap().clone();
-    artworks_data.images.append(&mut images);
+    artworks_data.id = id;
+    artworks_data.images = page_urls
+        .iter()
+        .map(|urls| urls.get("original").cloned().unwrap_or_default())
+        .collect();
+    artworks_data.page_urls = page_urls;
 
     Ok(artworks_data)
 }
 
 pub async fn get_artworks_image_data(id: usize) -> reqwest::Result<Vec<String>> {
-    let mut data = reqwest::get(format!("https://www.pixiv.net/ajax/illust/{}/pages", id))
+    let images = get_artworks_page_urls(id)
         .await?
-        .error_for_status()?
-        .json::<Api<Vec<ArtworkPagesData>>>()
-        .await?;
-    let images = data
-        .body
         .iter_mut()
-        .map(|image| image.urls.get("original").unwrap().to_string())
+        .map(|urls| urls.remove("original").unwrap())
         .collect();
     Ok(images)
 }
 
+/// Urls of every size of every page.
+pub async fn get_artworks_page_urls(id: usize) -> reqwest::Result<Vec<HashMap<String, String>>> {
+    let data = client::api()
+        .get(format!("https://www.pixiv.net/ajax/illust/{}/pages", id))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Api<Vec<ArtworkPagesData>>>()
+        .await?;
+    Ok(data.body.into_iter().map(|page| page.urls).collect())
+}
+
 #[cfg(test)]
 mod test {
     use super::get_artworks_data;
@@ -0,0 +1,103 @@
+use crate::client;
+use crate::data::{Api, BookmarkList, Content};
+
+/// Bookmarks fetched per request.
+const PAGE_SIZE: usize = 48;
+
+/// Public bookmarks of a user, newest first.
+pub struct Bookmarks {
+    user_id: usize,
+    queue: Vec<Content>,
+    offset: usize,
+    done: bool,
+}
+
+impl Bookmarks {
+    pub fn new(user_id: usize) -> Self {
+        Self {
+            user_id,
+            queue: vec![],
+            offset: 0,
+            done: false,
+        }
+    }
+
+    fn get_url(&self) -> String {
+        format!(
+            "https://www.pixiv.net/ajax/user/{}/illusts/bookmarks?tag=&offset={}&limit={}&rest=show",
+            self.user_id, self.offset, PAGE_SIZE
+        )
+    }
+
+    pub async fn next(&mut self) -> reqwest::Result<Option<Content>> {
+        while self.queue.is_empty() && !self.done {
+            let data = client::api()
+                .get(self.get_url())
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Api<BookmarkList>>()
+                .await?;
+
+            self.add_page(data.body);
+        }
+
+        if self.queue.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(self.queue.remove(0)))
+        }
+    }
+
+    fn add_page(&mut self, list: BookmarkList) {
+        self.offset += list.works.len();
+        self.done = list.works.is_empty() || self.offset >= list.total;
+        self.queue = list
+            .works
+            .into_iter()
+            // deleted or private artworks
+            .filter(|item| item.id != 0)
+            .map(Content::from)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Bookmarks;
+    use crate::data::{Api, BookmarkList};
+
+    #[tokio::test]
+    async fn test() {
+        let mut bookmarks = Bookmarks::new(3115085);
+        while bookmarks.next().await.unwrap().is_some() {}
+    }
+
+    #[test]
+    fn test_bookmark_list() {
+        let json = r#"{"error":false,"message":"","body":{"works":[
+            {"id":"106465672","title":"t","url":"https://i.pximg.net/c/250x250_80_a2/img-master/img/2023/03/23/00/05/02/106465672_p0_square1200.jpg",
+            "userName":"u","tags":["オリジナル","女の子"],"width":1200,"height":1600,"pageCount":2,
+            "xRestrict":0,"aiType":1,"isMasked":false},
+            {"title":"-----","url":"","userName":"","tags":[],"isMasked":true}],
+            "total":2}}"#;
+        let list = serde_json::from_str::<Api<BookmarkList>>(json)
+            .unwrap()
+            .body;
+        let mut bookmarks = Bookmarks::new(1);
+
+        bookmarks.add_page(list);
+
+        assert_eq!(bookmarks.offset, 2);
+        assert!(bookmarks.done);
+        assert_eq!(bookmarks.queue.len(), 1);
+        let content = &bookmarks.queue[0];
+        assert_eq!(content.illust_id, 106465672);
+        assert_eq!(content.tags, ["オリジナル", "女の子"]);
+        assert_eq!(
+            (content.width, content.height, content.page_count),
+            (1200, 1600, 2)
+        );
+        assert_eq!((content.x_restrict, content.ai_type), (Some(0), Some(1)));
+    }
+}
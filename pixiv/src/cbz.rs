@@ -0,0 +1,185 @@
+use std::{
+    fs::{rename, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::data::ArtworksData;
+
+/// Packs `pages` in order into the `.cbz` at `path` with a `ComicInfo.xml`, as read by
+/// comic servers such as Komga and Kavita.
+///
+/// Pages are named `001.jpg`, `002.png`, ... so every reader sorts them right.
+pub fn write_cbz(path: &Path, data: &ArtworksData, pages: &[PathBuf]) -> io::Result<()> {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let mut zip = ZipWriter::new(File::create(&part)?);
+    // images are already compressed
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+
+    for (index, page) in pages.iter().enumerate() {
+        zip.start_file(page_name(index, pages.len(), page), stored)?;
+        io::copy(&mut File::open(page)?, &mut zip)?;
+    }
+    zip.start_file("ComicInfo.xml", FileOptions::default())?;
+    zip.write_all(comic_info(data).as_bytes())?;
+    zip.finish()?;
+
+    rename(&part, path)
+}
+
+/// The ComicInfo 2.0 metadata of an artwork.
+pub fn comic_info(data: &ArtworksData) -> String {
+    let mut fields = vec![("Title", data.title.clone())];
+
+    match &data.series {
+        Some(series) => {
+            fields.push(("Series", series.title.clone()));
+            fields.push(("Number", series.order.to_string()));
+        }
+        None => fields.push(("Series", data.title.clone())),
+    }
+    let summary = html_to_text(&data.description);
+    if !summary.is_empty() {
+        fields.push(("Summary", summary));
+    }
+    // e.g. 2023-03-20T15:00:00+00:00
+    let date = data.create_date.get(..10).unwrap_or_default().split('-');
+    for (name, value) in ["Year", "Month", "Day"].into_iter().zip(date) {
+        if let Ok(value) = value.parse::<u32>() {
+            fields.push((name, value.to_string()));
+        }
+    }
+    fields.push(("Writer", data.user_name.clone()));
+    let tags = data
+        .tags
+        .tags
+        .iter()
+        .map(|tag| tag.tag.clone())
+        .collect::<Vec<_>>();
+    if !tags.is_empty() {
+        fields.push(("Tags", tags.join(",")));
+    }
+    fields.push(("Web", format!("https://www.pixiv.net/artworks/{}", data.id)));
+    fields.push(("PageCount", data.page_count.to_string()));
+    fields.push(("Manga", "Yes".to_string()));
+    fields.push((
+        "AgeRating",
+        match data.x_restrict {
+            0 => "Everyone",
+            1 => "R18+",
+            _ => "Adults Only 18+",
+        }
+        .to_string(),
+    ));
+
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+        "<ComicInfo xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" ",
+        "xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n"
+    ));
+    for (name, value) in fields {
+        xml.push_str(&format!("  <{0}>{1}</{0}>\n", name, escape_xml(&value)));
+    }
+    xml.push_str("</ComicInfo>\n");
+
+    xml
+}
+
+fn page_name(index: usize, count: usize, page: &Path) -> String {
+    let width = count.to_string().len().max(3);
+    let ext = page
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    format!("{:0width$}.{}", index + 1, ext, width = width)
+}
+
+/// Captions are HTML with `<br />` line breaks and links.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+
+    for c in html.replace("<br />", "\n").replace("<br>", "\n").chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env::temp_dir,
+        fs::{create_dir_all, remove_dir_all, write, File},
+        io::Read,
+    };
+
+    use zip::ZipArchive;
+
+    use super::{comic_info, write_cbz};
+    use crate::{data::ArtworksData, job::package_name};
+
+    #[test]
+    fn test_cbz() {
+        let json = r#"{"illustId":"10","title":"a/b","description":"x &amp; y<br />z",
+            "userId":"2","userName":"u","createDate":"2023-03-20T15:00:00+00:00",
+            "xRestrict":1,"pageCount":2,"tags":{"tags":[{"tag":"漫画"},{"tag":"<3"}]},
+            "seriesNavData":{"seriesId":"77","title":"S","order":4}}"#;
+        let data = serde_json::from_str::<ArtworksData>(json).unwrap();
+
+        assert_eq!(package_name(&data, "cbz"), "S 004 a_b-10.cbz");
+        let xml = comic_info(&data);
+        assert!(xml.contains("<Series>S</Series>\n  <Number>4</Number>"));
+        assert!(xml.contains("<Summary>x &amp; y\nz</Summary>"));
+        assert!(xml.contains("<Year>2023</Year>\n  <Month>3</Month>\n  <Day>20</Day>"));
+        assert!(xml.contains("<Tags>漫画,&lt;3</Tags>"));
+        assert!(xml.contains("<AgeRating>R18+</AgeRating>"));
+
+        let dir = temp_dir().join(format!("x-pixiv-cbz-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let pages = (0..2)
+            .map(|index| {
+                let path = dir.join(format!("a-{}.PNG", index));
+                write(&path, [index as u8]).unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+        let path = dir.join("a.cbz");
+        write_cbz(&path, &data, &pages).unwrap();
+
+        let mut zip = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let names = zip.file_names().map(String::from).collect::<Vec<_>>();
+        let mut second = vec![];
+        zip.by_name("002.png")
+            .unwrap()
+            .read_to_end(&mut second)
+            .unwrap();
+        remove_dir_all(&dir).ok();
+
+        assert_eq!(names.len(), 3);
+        assert_eq!(second, [1]);
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::time::sleep;
+
+const RUNNING: u8 = 0;
+const PAUSED: u8 = 1;
+const CANCELLED: u8 = 2;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlState {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+/// Pauses, resumes or cancels downloads, clones share the same state.
+///
+/// Downloads check it between chunks, a cancelled download can't be resumed.
+#[derive(Clone, Debug, Default)]
+pub struct Control {
+    state: Arc<AtomicU8>,
+}
+
+impl Control {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> ControlState {
+        match self.state.load(Ordering::Relaxed) {
+            RUNNING => ControlState::Running,
+            PAUSED => ControlState::Paused,
+            _ => ControlState::Cancelled,
+        }
+    }
+
+    pub fn pause(&self) {
+        self.transition(RUNNING, PAUSED);
+    }
+
+    pub fn resume(&self) {
+        self.transition(PAUSED, RUNNING);
+    }
+
+    pub fn toggle_pause(&self) {
+        if self.state() == ControlState::Paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.state.store(CANCELLED, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state() == ControlState::Cancelled
+    }
+
+    /// Waits while paused, returns false once cancelled.
+    pub async fn proceed(&self) -> bool {
+        loop {
+            match self.state() {
+                ControlState::Running => return true,
+                ControlState::Cancelled => return false,
+                ControlState::Paused => sleep(Duration::from_millis(100)).await,
+            }
+        }
+    }
+
+    /// Something besides the caller still holds this control.
+    pub(crate) fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.state) > 1
+    }
+
+    fn transition(&self, from: u8, to: u8) {
+        self.state
+            .compare_exchange(from, to, Ordering::Relaxed, Ordering::Relaxed)
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Control, ControlState};
+
+    #[tokio::test]
+    async fn test_control() {
+        let control = Control::new();
+        let clone = control.clone();
+
+        clone.pause();
+        assert_eq!(control.state(), ControlState::Paused);
+        control.toggle_pause();
+        assert!(control.proceed().await);
+
+        control.cancel();
+        control.resume();
+        assert!(clone.is_cancelled());
+        assert!(!clone.proceed().await);
+    }
+}
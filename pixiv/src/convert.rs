@@ -0,0 +1,381 @@
+use std::{
+    env::temp_dir,
+    fmt::Display,
+    fs::{self, rename},
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, GenericImageView, ImageFormat,
+    Rgb, RgbImage,
+};
+use serde::{Deserialize, Serialize};
+
+/// Quality of converted images when none is given.
+pub const DEFAULT_QUALITY: u8 = 85;
+/// Encoder of libjxl that JPEG XL images are made with, looked up in `PATH`.
+pub const CJXL: &str = "cjxl";
+
+/// Formats downloaded images can be converted to.
+///
+/// JPEG XL has no encoder written in Rust yet, it needs the `cjxl` tool of libjxl.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConvertFormat {
+    Jpeg,
+    Webp,
+    Avif,
+    Jxl,
+}
+
+/// How downloaded images are converted.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ConvertOptions {
+    pub format: ConvertFormat,
+    /// `1` to `100`
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    /// longest side in pixels, larger images are scaled down
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u32>,
+    /// keep the downloaded file next to the converted one
+    #[serde(default)]
+    pub keep_original: bool,
+}
+
+/// A converted download, as recorded in the archive.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Conversion {
+    pub format: ConvertFormat,
+    pub quality: u8,
+    /// the downloaded file
+    pub original: PathBuf,
+    pub original_bytes: u64,
+    /// whether `original` still exists
+    pub original_kept: bool,
+}
+
+fn default_quality() -> u8 {
+    DEFAULT_QUALITY
+}
+
+impl ConvertFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConvertFormat::Jpeg => "jpg",
+            ConvertFormat::Webp => "webp",
+            ConvertFormat::Avif => "avif",
+            ConvertFormat::Jxl => "jxl",
+        }
+    }
+
+    /// Whether images can be encoded in the format, `cjxl` may be missing for JPEG XL.
+    pub fn is_available(&self) -> bool {
+        match self {
+            ConvertFormat::Jxl => Command::new(CJXL).arg("--version").output().is_ok(),
+            _ => true,
+        }
+    }
+}
+
+impl ConvertOptions {
+    /// Where [`ConvertOptions::convert_file`] leaves the image at `path`, a resized image
+    /// in the same format and files that aren't still images keep their path.
+    pub fn converted_path(&self, path: &Path) -> PathBuf {
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match ext.as_str() {
+            "jpg" | "jpeg" if self.format == ConvertFormat::Jpeg => path.to_path_buf(),
+            "webp" if self.format == ConvertFormat::Webp => path.to_path_buf(),
+            "jpg" | "jpeg" | "png" | "webp" => path.with_extension(self.format.extension()),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Converts the image at `path` into a file with the extension of the format next to
+    /// it, returns the new file or `None` if the image is left as it is.
+    ///
+    /// Files that aren't still images, such as the zip of an ugoira or a possibly animated
+    /// GIF, and images already in the format that fit into `max_size` are left as they are.
+    pub fn convert_file(&self, path: &Path) -> io::Result<Option<(PathBuf, Conversion)>> {
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let same_format = match ext.as_str() {
+            "jpg" | "jpeg" => self.format == ConvertFormat::Jpeg,
+            "webp" => self.format == ConvertFormat::Webp,
+            "png" => false,
+            _ => return Ok(None),
+        };
+        let fits = |(width, height): (u32, u32)| {
+            self.max_size
+                .is_none_or(|max_size| width.max(height) <= max_size)
+        };
+        if same_format && fits(image::image_dimensions(path).map_err(invalid_data)?) {
+            return Ok(None);
+        }
+
+        let image = image::open(path).map_err(invalid_data)?;
+        let image = match self.max_size {
+            Some(max_size) if !fits(image.dimensions()) => {
+                image.resize(max_size, max_size, FilterType::Lanczos3)
+            }
+            _ => image,
+        };
+        let bytes = self.encode(&image)?;
+        let original_bytes = fs::metadata(path)?.len();
+
+        let target = path.with_extension(self.format.extension());
+        let mut part = target.as_os_str().to_owned();
+        part.push(".part");
+        fs::write(&part, &bytes)?;
+        rename(&part, &target)?;
+        // a resized image in the same format replaces the download
+        let original_kept =
+            target != path && (self.keep_original || fs::remove_file(path).is_err());
+
+        Ok(Some((
+            target,
+            Conversion {
+                format: self.format,
+                quality: self.quality,
+                original: path.to_path_buf(),
+                original_bytes,
+                original_kept,
+            },
+        )))
+    }
+
+    /// Encodes `image` in the format and quality.
+    pub fn encode(&self, image: &DynamicImage) -> io::Result<Vec<u8>> {
+        let quality = self.quality.clamp(1, 100);
+        let (width, height) = image.dimensions();
+
+        match self.format {
+            ConvertFormat::Jpeg => {
+                let mut bytes = Cursor::new(vec![]);
+                JpegEncoder::new_with_quality(&mut bytes, quality)
+                    .encode_image(&flatten(image))
+                    .map_err(invalid_data)?;
+                Ok(bytes.into_inner())
+            }
+            ConvertFormat::Webp => {
+                let encoded = if image.color().has_alpha() {
+                    let pixels = image.to_rgba8();
+                    webp::Encoder::from_rgba(&pixels, width, height)
+                        .encode_simple(false, quality as f32)
+                        .map(|memory| memory.to_vec())
+                } else {
+                    let pixels = image.to_rgb8();
+                    webp::Encoder::from_rgb(&pixels, width, height)
+                        .encode_simple(false, quality as f32)
+                        .map(|memory| memory.to_vec())
+                };
+                encoded.map_err(|err| invalid_data(format!("webp: {:?}", err)))
+            }
+            ConvertFormat::Avif => {
+                // one thread per image, the pool of the job manager decides how many run
+                let encoder = ravif::Encoder::new()
+                    .with_quality(quality as f32)
+                    .with_alpha_quality(quality as f32)
+                    .with_speed(6)
+                    .with_num_threads(Some(1));
+                let (width, height) = (width as usize, height as usize);
+                let encoded = if image.color().has_alpha() {
+                    let pixels = image
+                        .to_rgba8()
+                        .pixels()
+                        .map(|pixel| ravif::RGBA8::new(pixel[0], pixel[1], pixel[2], pixel[3]))
+                        .collect::<Vec<_>>();
+                    encoder.encode_rgba(ravif::Img::new(&pixels[..], width, height))
+                } else {
+                    let pixels = image
+                        .to_rgb8()
+                        .pixels()
+                        .map(|pixel| ravif::RGB8::new(pixel[0], pixel[1], pixel[2]))
+                        .collect::<Vec<_>>();
+                    encoder.encode_rgb(ravif::Img::new(&pixels[..], width, height))
+                };
+                encoded
+                    .map(|encoded| encoded.avif_file)
+                    .map_err(invalid_data)
+            }
+            ConvertFormat::Jxl => encode_jxl(image, quality),
+        }
+    }
+}
+
+/// Passes `image` to `cjxl` through a PNG in the temporary folder.
+fn encode_jxl(image: &DynamicImage, quality: u8) -> io::Result<Vec<u8>> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let name = format!(
+        "x-pixiv-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    );
+    let png = temp_dir().join(format!("{}.png", name));
+    let jxl = temp_dir().join(format!("{}.jxl", name));
+
+    image
+        .save_with_format(&png, ImageFormat::Png)
+        .map_err(invalid_data)?;
+    let output = Command::new(CJXL)
+        .arg(&png)
+        .arg(&jxl)
+        .args(["-q", &quality.to_string(), "--quiet"])
+        .output();
+    fs::remove_file(&png).ok();
+
+    let output = match output {
+        Ok(output) => output,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "cjxl of libjxl is needed to convert to JPEG XL",
+            ))
+        }
+        Err(err) => return Err(err),
+    };
+    let bytes = if output.status.success() {
+        fs::read(&jxl)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(invalid_data(format!("cjxl: {}", stderr.trim())))
+    };
+    fs::remove_file(&jxl).ok();
+
+    bytes
+}
+
+/// JPEG has no transparency, transparent pixels become white.
+fn flatten(image: &DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+    let rgba = image.to_rgba8();
+
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let pixel = rgba.get_pixel(x, y);
+        let alpha = pixel[3] as u32;
+        let blend = |value: u8| ((value as u32 * alpha + 255 * (255 - alpha)) / 255) as u8;
+        Rgb([blend(pixel[0]), blend(pixel[1]), blend(pixel[2])])
+    })
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+impl Display for ConvertFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ConvertFormat::Jpeg => "jpeg",
+                ConvertFormat::Webp => "webp",
+                ConvertFormat::Avif => "avif",
+                ConvertFormat::Jxl => "jxl",
+            }
+        )
+    }
+}
+
+impl FromStr for ConvertFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jpeg" | "jpg" => Ok(ConvertFormat::Jpeg),
+            "webp" => Ok(ConvertFormat::Webp),
+            "avif" => Ok(ConvertFormat::Avif),
+            "jxl" | "jpegxl" => Ok(ConvertFormat::Jxl),
+            _ => Err(format!("unknown convert format: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env::temp_dir,
+        fs::{create_dir_all, remove_dir_all},
+    };
+
+    use image::{Rgba, RgbaImage};
+
+    use super::{ConvertFormat, ConvertOptions};
+
+    #[test]
+    fn test_convert() {
+        let dir = temp_dir().join(format!("x-pixiv-convert-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let png = dir.join("1_p0.png");
+        RgbaImage::from_fn(64, 32, |x, y| Rgba([x as u8 * 4, y as u8 * 8, 90, 200]))
+            .save(&png)
+            .unwrap();
+        let zip = dir.join("2_ugoira.zip");
+        std::fs::write(&zip, b"PK").unwrap();
+
+        let options = |format, max_size, keep_original| ConvertOptions {
+            format,
+            quality: 80,
+            max_size,
+            keep_original,
+        };
+        let (webp, conversion) = options(ConvertFormat::Webp, Some(16), true)
+            .convert_file(&png)
+            .unwrap()
+            .unwrap();
+        let webp_size = image::image_dimensions(&webp).unwrap();
+        let same = options(ConvertFormat::Webp, None, false)
+            .convert_file(&webp)
+            .unwrap();
+        let (jpeg, jpeg_conversion) = options(ConvertFormat::Jpeg, None, false)
+            .convert_file(&png)
+            .unwrap()
+            .unwrap();
+        let jpeg_size = image::image_dimensions(&jpeg).unwrap();
+        let zip = options(ConvertFormat::Jpeg, None, false).convert_file(&zip);
+        let avif = options(ConvertFormat::Avif, None, false)
+            .encode(&image::open(&jpeg).unwrap())
+            .unwrap();
+        let jxl = options(ConvertFormat::Jxl, None, false).encode(&image::open(&jpeg).unwrap());
+        let png_exists = png.exists();
+        remove_dir_all(&dir).ok();
+
+        assert_eq!(webp, dir.join("1_p0.webp"));
+        assert_eq!(
+            options(ConvertFormat::Webp, Some(16), true).converted_path(&png),
+            webp
+        );
+        assert_eq!(webp_size, (16, 8));
+        assert!(conversion.original_kept);
+        assert!(same.is_none());
+        assert_eq!(jpeg_size, (64, 32));
+        assert!(!jpeg_conversion.original_kept && !png_exists);
+        assert!(zip.unwrap().is_none());
+        assert_eq!(
+            options(ConvertFormat::Jpeg, None, false).converted_path(&jpeg),
+            jpeg
+        );
+        assert_eq!(&avif[4..12], b"ftypavif");
+        assert_eq!("jxl".parse::<ConvertFormat>(), Ok(ConvertFormat::Jxl));
+        // a bare codestream or the ISO BMFF container
+        match jxl {
+            Ok(jxl) => assert!(jxl.starts_with(&[0xff, 0x0a]) || jxl[4..8] == *b"JXL "),
+            Err(err) => {
+                assert!(!ConvertFormat::Jxl.is_available());
+                assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{collections::HashMap, str::FromStr};
 
 #[derive(Serialize, Deserialize)]
 pub struct Api<T> {
@@ -22,10 +22,68 @@ pub struct ArtworksData {
     #[serde(skip)]
     #[serde(default)]
     pub images: Vec<String>,
+    /// every size of every page, keyed by `original`, `regular`, `small` and `thumb_mini`
+    #[serde(skip)]
+    #[serde(default)]
+    pub page_urls: Vec<HashMap<String, String>>,
+    #[serde(rename = "illustId", deserialize_with = "from_str", default)]
+    pub id: usize,
     pub title: String,
     pub description: String,
+    #[serde(rename = "userId", deserialize_with = "from_str", default)]
+    pub user_id: usize,
     #[serde(rename = "userName")]
     pub user_name: String,
+    #[serde(default)]
+    pub tags: Tags,
+    #[serde(rename = "createDate", default)]
+    pub create_date: String,
+    #[serde(rename = "uploadDate", default)]
+    pub upload_date: String,
+    #[serde(rename = "illustType", default)]
+    pub illust_type: u8,
+    #[serde(rename = "xRestrict", default)]
+    pub x_restrict: u8,
+    #[serde(rename = "aiType", default)]
+    pub ai_type: u8,
+    #[serde(rename = "pageCount", default)]
+    pub page_count: usize,
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    #[serde(rename = "bookmarkCount", default)]
+    pub bookmark_count: usize,
+    #[serde(rename = "likeCount", default)]
+    pub like_count: usize,
+    #[serde(rename = "viewCount", default)]
+    pub view_count: usize,
+    /// set for an episode of a manga series
+    #[serde(rename = "seriesNavData", default)]
+    pub series: Option<SeriesNav>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SeriesNav {
+    #[serde(rename = "seriesId", deserialize_with = "number")]
+    pub series_id: usize,
+    pub title: String,
+    /// episode number, starting at 1
+    #[serde(default)]
+    pub order: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tags {
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Tag {
+    pub tag: String,
+    #[serde(default)]
+    pub translation: Option<HashMap<String, String>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -40,15 +98,143 @@ pub struct RankList {
     pub date: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Content {
     pub title: String,
     pub illust_id: usize,
     pub url: String,
     pub user_name: String,
     pub tags: Vec<String>,
+    /// size of the first page, `0` when the listing doesn't have it
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    #[serde(rename = "illust_page_count", deserialize_with = "number", default)]
+    pub page_count: usize,
+    /// only known in search and bookmark listings
+    #[serde(default)]
+    pub x_restrict: Option<u8>,
+    #[serde(default)]
+    pub ai_type: Option<u8>,
+    /// position in a ranking listing
+    #[serde(default)]
+    pub rank: Option<usize>,
+}
+
+/// https://www.pixiv.net/ajax/search/artworks/{word}?word={word}&p={}
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchResult {
+    #[serde(rename = "illustManga")]
+    pub illust_manga: SearchList,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchList {
+    pub data: Vec<SearchItem>,
+    pub total: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchItem {
+    /// ad containers have no id
+    #[serde(deserialize_with = "from_str", default)]
+    pub id: usize,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(rename = "userName", default)]
+    pub user_name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    #[serde(rename = "pageCount", default)]
+    pub page_count: usize,
+    #[serde(rename = "xRestrict", default)]
+    pub x_restrict: u8,
+    #[serde(rename = "aiType", default)]
+    pub ai_type: u8,
+}
+
+/// https://www.pixiv.net/ajax/user/{id}/illusts/bookmarks?tag=&offset={}&limit={}&rest=show
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BookmarkList {
+    pub works: Vec<SearchItem>,
+    pub total: usize,
+}
+
+/// https://www.pixiv.net/ajax/series/{id}?p={}
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SeriesBody {
+    pub page: SeriesPage,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SeriesPage {
+    pub series: Vec<SeriesWork>,
+    #[serde(deserialize_with = "number")]
+    pub total: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SeriesWork {
+    #[serde(rename = "workId", deserialize_with = "number")]
+    pub work_id: usize,
+    /// episode number, starting at 1
+    pub order: usize,
+}
+
+impl From<SearchItem> for Content {
+    fn from(item: SearchItem) -> Self {
+        Self {
+            title: item.title,
+            illust_id: item.id,
+            url: item.url,
+            user_name: item.user_name,
+            tags: item.tags,
+            width: item.width,
+            height: item.height,
+            page_count: item.page_count,
+            x_restrict: Some(item.x_restrict),
+            ai_type: Some(item.ai_type),
+            rank: None,
+        }
+    }
 }
 
 // https://www.pixiv.net/ajax/user/3115085/profile/illusts?ids%5B%5D={id}&work_category=illustManga&is_first_page=1
 #[derive(Serialize, Deserialize)]
 pub struct ImagesInfo {}
+
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+/// A number sent as a number or as a string.
+fn number<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Number(usize),
+        String(String),
+    }
+
+    match Number::deserialize(deserializer)? {
+        Number::Number(number) => Ok(number),
+        Number::String(number) => number.parse().map_err(serde::de::Error::custom),
+    }
+}
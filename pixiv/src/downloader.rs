@@ -1,56 +1,268 @@
 use std::{
-    fs::{create_dir_all, File},
-    io::Write,
-    path::PathBuf,
+    ffi::OsString,
+    fmt::Display,
+    fs::{create_dir_all, read, remove_file, rename, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
 };
 
 use futures_util::StreamExt;
+use reqwest::{
+    header::{CONTENT_LENGTH, RANGE},
+    StatusCode,
+};
+use sha2::{Digest, Sha256};
+
+use crate::client;
+use crate::control::Control;
+use crate::host::{self, ImageHost};
+use crate::job::error_kind;
+
+/// A finished download.
+#[derive(Clone, Debug)]
+pub struct Downloaded {
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// hex encoded SHA-256 of the file
+    pub sha256: String,
+}
+
+/// A download that failed on the request or while writing the file.
+#[derive(Debug)]
+pub enum DownloadError {
+    Request(reqwest::Error),
+    Io(io::Error),
+}
+
+impl DownloadError {
+    /// Short machine readable name of the error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DownloadError::Request(err) => error_kind(err),
+            DownloadError::Io(_) => "io",
+        }
+    }
+}
 
+impl From<reqwest::Error> for DownloadError {
+    fn from(err: reqwest::Error) -> Self {
+        DownloadError::Request(err)
+    }
+}
+
+impl From<io::Error> for DownloadError {
+    fn from(err: io::Error) -> Self {
+        DownloadError::Io(err)
+    }
+}
+
+impl Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadError::Request(err) => err.fmt(f),
+            DownloadError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DownloadError::Request(err) => Some(err),
+            DownloadError::Io(err) => Some(err),
+        }
+    }
+}
+
+/// Downloads `url`, trying the configured image hosts in order.
 pub async fn downloader<F, FT>(
     path: PathBuf,
     url: String,
     progress: F,
     total: FT,
-) -> reqwest::Result<()>
+) -> Result<Downloaded, DownloadError>
 where
     F: Fn(u64, u64),
     FT: Fn(u64),
 {
-    create_dir_all(path.parent().unwrap()).unwrap();
-
-    let client = reqwest::Client::new();
-    let mut file = File::create(path);
-    let response = client
-        .get(url)
-        .header("referer", "https://www.pixiv.net/")
-        .send()
-        .await?;
-    let Some(total_size) = response.content_length() else {
-        return Ok(());
+    let downloaded = download_with(path, url, &Control::new(), false, progress, total).await?;
+
+    Ok(downloaded.expect("a new control is never cancelled"))
+}
+
+/// Downloads `url` into `{path}.part` and renames it once complete, returns `None` when
+/// `control` was cancelled.
+///
+/// With `resume` an interrupted `.part` file is kept and continued with a range request,
+/// otherwise it is removed.
+pub async fn download_with<F, FT>(
+    path: PathBuf,
+    url: String,
+    control: &Control,
+    resume: bool,
+    progress: F,
+    total: FT,
+) -> Result<Option<Downloaded>, DownloadError>
+where
+    F: Fn(u64, u64),
+    FT: Fn(u64),
+{
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+
+    let part = part_path(&path);
+    let mut result = Ok(None);
+    for image_host in host::image_hosts() {
+        result = download_from(&part, &image_host, &url, control, resume, &progress, &total).await;
+        match &result {
+            Ok(_) => break,
+            // another host writes into the same file
+            Err(DownloadError::Io(_)) => break,
+            // the file changed on the server, the kept part is useless
+            Err(DownloadError::Request(err))
+                if err.status() == Some(StatusCode::RANGE_NOT_SATISFIABLE) =>
+            {
+                remove_file(&part).ok();
+            }
+            Err(_) => {}
+        }
+        if let Err(DownloadError::Request(err)) = &result {
+            if is_host_failure(err) {
+                host::demote(&image_host);
+            }
+        }
+    }
+
+    match result {
+        Ok(Some(downloaded)) => {
+            rename(&part, &path)?;
+            Ok(Some(Downloaded { path, ..downloaded }))
+        }
+        result => {
+            if !resume {
+                remove_file(&part).ok();
+            }
+            result
+        }
+    }
+}
+
+/// Size of `url` from a HEAD request, trying the configured image hosts in order.
+pub async fn content_length(url: &str) -> reqwest::Result<Option<u64>> {
+    let mut result = Ok(None);
+    for image_host in host::image_hosts() {
+        let mut request = client::image().head(image_host.rewrite(url));
+        if image_host.referer {
+            request = request.header("referer", "https://www.pixiv.net/");
+        }
+        match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(response) => {
+                return Ok(response
+                    .headers()
+                    .get(CONTENT_LENGTH)
+                    .and_then(|length| length.to_str().ok())
+                    .and_then(|length| length.parse().ok()))
+            }
+            Err(err) => {
+                if is_host_failure(&err) {
+                    host::demote(&image_host);
+                }
+                result = Err(err);
+            }
+        }
+    }
+
+    result
+}
+
+/// Whether the host itself failed, a missing or forbidden image is tried on the next host
+/// without moving this one down.
+fn is_host_failure(err: &reqwest::Error) -> bool {
+    err.is_connect()
+        || err.is_timeout()
+        || err.status().is_some_and(|status| status.is_server_error())
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut part = OsString::from(path.as_os_str());
+    part.push(".part");
+    PathBuf::from(part)
+}
+
+async fn download_from<F, FT>(
+    part: &Path,
+    image_host: &ImageHost,
+    url: &str,
+    control: &Control,
+    resume: bool,
+    progress: &F,
+    total: &FT,
+) -> Result<Option<Downloaded>, DownloadError>
+where
+    F: Fn(u64, u64),
+    FT: Fn(u64),
+{
+    let existing = if resume {
+        part.metadata().map(|metadata| metadata.len()).unwrap_or(0)
+    } else {
+        0
     };
-    total(total_size);
-    if let Ok(file) = &mut file {
-        let mut byte_stream = response.bytes_stream();
-        let mut now_size: u64 = 0;
-        while let Some(byte) = byte_stream.next().await {
-            let byte = byte?;
-            now_size += byte.len() as u64;
+    let client = client::image();
+    let mut request = client.get(image_host.rewrite(url));
+    if image_host.referer {
+        request = request.header("referer", "https://www.pixiv.net/");
+    }
+    if existing > 0 {
+        request = request.header(RANGE, format!("bytes={}-", existing));
+    }
+    let response = request.send().await?.error_for_status()?;
+    let mut hasher = Sha256::new();
+    let mut now_size: u64 = 0;
+    let mut file = if existing > 0 && response.status() == StatusCode::PARTIAL_CONTENT {
+        hasher.update(read(part)?);
+        now_size = existing;
+        OpenOptions::new().append(true).open(part)?
+    } else {
+        File::create(part)?
+    };
+    let total_size = response.content_length().map(|length| length + now_size);
+    if let Some(total_size) = total_size {
+        total(total_size);
+    }
+    let mut byte_stream = response.bytes_stream();
+    while let Some(byte) = byte_stream.next().await {
+        if !control.proceed().await {
+            return Ok(None);
+        }
+        let byte = byte?;
+        now_size += byte.len() as u64;
+        if let Some(total_size) = total_size {
             progress(now_size, total_size);
-            file.write(&byte[..]).unwrap();
         }
+        hasher.update(&byte);
+        file.write_all(&byte[..])?;
     }
 
-    Ok(())
+    Ok(Some(Downloaded {
+        path: part.to_path_buf(),
+        bytes: now_size,
+        sha256: hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    }))
 }
 
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
+    use std::{env::temp_dir, fs, path::PathBuf};
 
-    use super::downloader;
+    use super::{downloader, DownloadError};
 
     #[tokio::test]
-    async fn downloader_test() -> reqwest::Result<()> {
+    async fn downloader_test() -> Result<(), DownloadError> {
         downloader(
             PathBuf::new(),
             "https://i.pximg.net/img-original/img/2023/03/23/00/05/02/106465672_p0.png".to_string(),
@@ -60,4 +272,22 @@ mod test {
         .await?;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_io_error() {
+        let file = temp_dir().join(format!("x-pixiv-downloader-{}", std::process::id()));
+        fs::write(&file, b"").unwrap();
+
+        // the folder can't be created inside a file
+        let result = downloader(
+            file.join("1_p0.png"),
+            "https://i.pximg.net/img-original/img/1_p0.png".to_string(),
+            |_, _| {},
+            |_| {},
+        )
+        .await;
+        fs::remove_file(&file).ok();
+
+        assert_eq!(result.unwrap_err().kind(), "io");
+    }
 }
@@ -0,0 +1,276 @@
+use std::{collections::BTreeMap, fmt::Display, io, path::Path, str::FromStr};
+
+use image::imageops::{self, FilterType};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::archive::{Archive, ArchiveEntry, ArchiveFile};
+
+/// Hamming distance up to which two images count as the same when none is given.
+pub const DEFAULT_DISTANCE: u32 = 6;
+
+/// A 64 bit difference hash, similar images have hashes that differ in few bits.
+///
+/// Stored as 16 hex digits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dhash(pub u64);
+
+/// What happens to a downloaded image that looks like an image of another artwork.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DupeAction {
+    /// keep the file and report it
+    Flag,
+    /// report it and remove the file
+    Skip,
+}
+
+impl Dhash {
+    /// Hashes an encoded image, e.g. a downloaded file.
+    ///
+    /// The image is shrunk to 9x8 gray pixels and every bit says whether a pixel is
+    /// brighter than its right neighbour, so resizing and recompressing keep the hash.
+    pub fn new(bytes: &[u8]) -> image::ImageResult<Self> {
+        let gray = image::load_from_memory(bytes)?.to_luma8();
+        let small = imageops::resize(&gray, 9, 8, FilterType::Triangle);
+        let mut hash = 0;
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let brighter = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+                hash = hash << 1 | brighter as u64;
+            }
+        }
+
+        Ok(Self(hash))
+    }
+
+    /// Hashes the image at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+
+        Self::new(&bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Number of differing bits.
+    pub fn distance(&self, other: &Dhash) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+}
+
+impl Display for Dhash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for Dhash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16)
+            .map(Self)
+            .map_err(|_| format!("invalid dhash: {}", s))
+    }
+}
+
+impl Serialize for Dhash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Dhash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Display for DupeAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DupeAction::Flag => "flag",
+                DupeAction::Skip => "skip",
+            }
+        )
+    }
+}
+
+impl FromStr for DupeAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flag" => Ok(DupeAction::Flag),
+            "skip" => Ok(DupeAction::Skip),
+            _ => Err(format!("unknown dupe action: {}", s)),
+        }
+    }
+}
+
+/// The closest hashed image of another artwork within `max_distance`.
+pub fn find_similar(
+    archive: &Archive,
+    illust_id: usize,
+    hash: Dhash,
+    max_distance: u32,
+) -> Option<(&ArchiveEntry, &ArchiveFile, u32)> {
+    archive
+        .entries()
+        .filter(|entry| entry.illust_id != illust_id)
+        .flat_map(|entry| entry.files.iter().map(move |file| (entry, file)))
+        .filter_map(|(entry, file)| Some((entry, file, file.dhash?.distance(&hash))))
+        .filter(|(_, _, distance)| *distance <= max_distance)
+        .min_by_key(|(_, _, distance)| *distance)
+}
+
+/// Groups of hashed images of different artworks within `max_distance` of each other,
+/// also through other images of the group, ordered by artwork id and page.
+///
+/// Pages of the same artwork are only grouped through an image of another artwork.
+pub fn clusters(archive: &Archive, max_distance: u32) -> Vec<Vec<(&ArchiveEntry, &ArchiveFile)>> {
+    let files = archive
+        .entries()
+        .flat_map(|entry| entry.files.iter().map(move |file| (entry, file)))
+        .filter(|(_, file)| file.dhash.is_some())
+        .collect::<Vec<_>>();
+    let mut parents = (0..files.len()).collect::<Vec<_>>();
+
+    fn root(parents: &mut [usize], mut index: usize) -> usize {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
+        }
+        index
+    }
+
+    for (a, (entry_a, file_a)) in files.iter().enumerate() {
+        for (b, (entry_b, file_b)) in files.iter().enumerate().skip(a + 1) {
+            if entry_a.illust_id != entry_b.illust_id
+                && file_a.dhash.unwrap().distance(&file_b.dhash.unwrap()) <= max_distance
+            {
+                let (root_a, root_b) = (root(&mut parents, a), root(&mut parents, b));
+                parents[root_b] = root_a;
+            }
+        }
+    }
+
+    let mut groups = BTreeMap::<usize, Vec<_>>::new();
+    for (index, file) in files.iter().enumerate() {
+        groups
+            .entry(root(&mut parents, index))
+            .or_default()
+            .push(*file);
+    }
+
+    let mut clusters = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect::<Vec<_>>();
+    // entries and files are already ordered, so the first file of a group is its smallest
+    clusters.sort_by_key(|group| (group[0].0.illust_id, group[0].1.page));
+
+    clusters
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env::temp_dir, fs::remove_file, io::Cursor};
+
+    use image::{GrayImage, ImageOutputFormat, Luma};
+
+    use super::{clusters, find_similar, Dhash};
+    use crate::archive::{Archive, ArchiveEntry, ArchiveFile};
+
+    fn encode(image: &GrayImage, format: ImageOutputFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(vec![]);
+        image.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    fn entry(illust_id: usize, hashes: &[u64]) -> ArchiveEntry {
+        ArchiveEntry {
+            illust_id,
+            title: String::new(),
+            user_id: 0,
+            user_name: String::new(),
+            tags: vec![],
+            x_restrict: 0,
+            ai_type: 0,
+            rank: None,
+            downloaded_at: 0,
+            files: hashes
+                .iter()
+                .enumerate()
+                .map(|(page, hash)| ArchiveFile {
+                    page,
+                    url: String::new(),
+                    path: "".into(),
+                    bytes: 0,
+                    sha256: String::new(),
+                    dhash: Some(Dhash(*hash)),
+                    duplicate_of: None,
+                    conversion: None,
+                })
+                .collect(),
+            packages: vec![],
+        }
+    }
+
+    #[test]
+    fn test_dhash() {
+        let waves = |x: u32, y: u32| {
+            let value = (x as f32 / 25.0).sin() * (y as f32 / 30.0).cos();
+            Luma([(128.0 + 100.0 * value) as u8])
+        };
+        let image = GrayImage::from_fn(180, 160, waves);
+        let smaller = image::imageops::thumbnail(&image, 90, 80);
+        let other = GrayImage::from_fn(180, 160, |x, y| Luma([((x * 7) ^ (y * 13)) as u8]));
+
+        let hash = Dhash::new(&encode(&image, ImageOutputFormat::Png)).unwrap();
+        let resized = Dhash::new(&encode(&smaller, ImageOutputFormat::Jpeg(80))).unwrap();
+        let other = Dhash::new(&encode(&other, ImageOutputFormat::Png)).unwrap();
+
+        assert!(hash.distance(&resized) <= 2);
+        assert!(hash.distance(&other) > 10);
+        assert_eq!(hash.to_string().parse::<Dhash>(), Ok(hash));
+        assert!(Dhash::new(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_clusters() {
+        let path = temp_dir().join(format!("x-pixiv-dupes-{}.jsonl", std::process::id()));
+        let mut archive = Archive::open(&path).unwrap();
+        for entry in [
+            entry(1, &[0b1111, 0xff00]),
+            entry(2, &[0b0111]),
+            entry(3, &[0xff00_0000]),
+            entry(4, &[0b0011, 0xff01]),
+        ] {
+            archive.record(entry).unwrap();
+        }
+        remove_file(&path).ok();
+
+        let ids = clusters(&archive, 1)
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .map(|(entry, file)| (entry.illust_id, file.page))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![vec![(1, 0), (2, 0), (4, 0)], vec![(1, 1), (4, 1)]]
+        );
+
+        let (similar, file, distance) = find_similar(&archive, 2, Dhash(0b1110), 2).unwrap();
+        assert_eq!((similar.illust_id, file.page, distance), (1, 0, 1));
+        assert!(find_similar(&archive, 1, Dhash(0b1111), 0).is_none());
+    }
+}
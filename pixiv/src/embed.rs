@@ -0,0 +1,346 @@
+use std::{
+    fs::{read, write},
+    io,
+    path::Path,
+};
+
+use crate::data::ArtworksData;
+
+const XMP_JPEG_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const XMP_PNG_KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// Metadata embedded into the downloaded images.
+#[derive(Clone, Debug, Default)]
+pub struct EmbedMetadata {
+    pub title: String,
+    pub author: String,
+    pub source_url: String,
+    pub tags: Vec<String>,
+    pub description: String,
+}
+
+impl From<&ArtworksData> for EmbedMetadata {
+    fn from(data: &ArtworksData) -> Self {
+        Self {
+            title: data.title.clone(),
+            author: data.user_name.clone(),
+            source_url: format!("https://www.pixiv.net/artworks/{}", data.id),
+            tags: data.tags.tags.iter().map(|tag| tag.tag.clone()).collect(),
+            description: data.description.clone(),
+        }
+    }
+}
+
+impl EmbedMetadata {
+    pub fn xmp(&self) -> String {
+        let tags = self
+            .tags
+            .iter()
+            .map(|tag| format!("<rdf:li>{}</rdf:li>", escape(tag)))
+            .collect::<String>();
+
+        format!(
+            concat!(
+                "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>",
+                "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">",
+                "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">",
+                "<rdf:Description rdf:about=\"\"",
+                " xmlns:dc=\"http://purl.org/dc/elements/1.1/\"",
+                " xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\">",
+                "<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:title>",
+                "<dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>",
+                "<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:description>",
+                "<dc:subject><rdf:Bag>{}</rdf:Bag></dc:subject>",
+                "<dc:source>{}</dc:source>",
+                "<xmp:CreatorTool>x-pixiv</xmp:CreatorTool>",
+                "</rdf:Description>",
+                "</rdf:RDF>",
+                "</x:xmpmeta>",
+                "<?xpacket end=\"w\"?>"
+            ),
+            escape(&self.title),
+            escape(&self.author),
+            escape(&self.description),
+            tags,
+            escape(&self.source_url),
+        )
+    }
+
+    /// IPTC-IIM records in UTF-8, wrapped in a Photoshop resource block.
+    fn iptc(&self) -> Vec<u8> {
+        let mut records = vec![];
+        let mut push = |record: u8, dataset: u8, value: &[u8]| {
+            let len = value.len().min(u16::MAX as usize);
+            records.extend_from_slice(&[0x1c, record, dataset]);
+            records.extend_from_slice(&(len as u16).to_be_bytes());
+            records.extend_from_slice(&value[..len]);
+        };
+
+        push(1, 90, b"\x1b%G");
+        push(2, 0, &[0, 4]);
+        push(2, 5, self.title.as_bytes());
+        push(2, 80, self.author.as_bytes());
+        push(2, 115, self.source_url.as_bytes());
+        push(2, 120, self.description.as_bytes());
+        for tag in &self.tags {
+            push(2, 25, tag.as_bytes());
+        }
+
+        let mut resource = b"8BIM\x04\x04\0\0".to_vec();
+        resource.extend_from_slice(&(records.len() as u32).to_be_bytes());
+        resource.append(&mut records);
+        if resource.len() % 2 == 1 {
+            resource.push(0);
+        }
+
+        resource
+    }
+}
+
+/// Writes the metadata into the image at `path` without re-encoding it.
+///
+/// Returns `false` when the file is not a JPEG, PNG, WebP or GIF image.
+pub fn embed_file(path: &Path, metadata: &EmbedMetadata) -> io::Result<bool> {
+    let bytes = read(path)?;
+
+    match embed(&bytes, metadata)? {
+        Some(bytes) => {
+            write(path, bytes)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+pub fn embed(bytes: &[u8], metadata: &EmbedMetadata) -> io::Result<Option<Vec<u8>>> {
+    Ok(if bytes.starts_with(&[0xff, 0xd8]) {
+        Some(embed_jpeg(bytes, metadata)?)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(embed_png(bytes, metadata)?)
+    } else if bytes.len() > 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        Some(embed_webp(bytes, metadata)?)
+    } else if bytes.starts_with(b"GIF8") {
+        Some(embed_gif(bytes, metadata)?)
+    } else {
+        None
+    })
+}
+
+fn embed_jpeg(bytes: &[u8], metadata: &EmbedMetadata) -> io::Result<Vec<u8>> {
+    let xmp = [XMP_JPEG_HEADER, metadata.xmp().as_bytes()].concat();
+    let iptc = [b"Photoshop 3.0\0".as_slice(), &metadata.iptc()].concat();
+
+    if xmp.len() > 0xfffd || iptc.len() > 0xfffd {
+        return Err(invalid_data("metadata is too large for a JPEG segment"));
+    }
+
+    // keep JFIF / Exif segments first
+    let mut offset = 2;
+    while offset + 4 <= bytes.len()
+        && bytes[offset] == 0xff
+        && (bytes[offset + 1] == 0xe0 || bytes[offset + 1] == 0xe1)
+    {
+        offset += 2 + u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]) as usize;
+    }
+    if offset > bytes.len() {
+        return Err(invalid_data("truncated JPEG segment"));
+    }
+
+    let mut output = bytes[..offset].to_vec();
+    for (marker, payload) in [(0xe1, xmp), (0xed, iptc)] {
+        output.extend_from_slice(&[0xff, marker]);
+        output.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        output.extend_from_slice(&payload);
+    }
+    output.extend_from_slice(&bytes[offset..]);
+
+    Ok(output)
+}
+
+fn embed_png(bytes: &[u8], metadata: &EmbedMetadata) -> io::Result<Vec<u8>> {
+    // signature + IHDR chunk
+    let offset = 8 + 4 + 4 + 13 + 4;
+    if bytes.len() < offset || &bytes[12..16] != b"IHDR" {
+        return Err(invalid_data("PNG without IHDR chunk"));
+    }
+
+    let mut output = bytes[..offset].to_vec();
+    for (keyword, text) in [
+        ("Title", metadata.title.as_str()),
+        ("Author", metadata.author.as_str()),
+        ("Description", metadata.description.as_str()),
+        ("Source", metadata.source_url.as_str()),
+        (XMP_PNG_KEYWORD, metadata.xmp().as_str()),
+    ] {
+        let mut data = keyword.as_bytes().to_vec();
+        // null separator, no compression, empty language tag and translated keyword
+        data.extend_from_slice(&[0, 0, 0, 0, 0]);
+        data.extend_from_slice(text.as_bytes());
+
+        output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let chunk = [b"iTXt".as_slice(), &data].concat();
+        output.extend_from_slice(&chunk);
+        output.extend_from_slice(&crc32(&chunk).to_be_bytes());
+    }
+    output.extend_from_slice(&bytes[offset..]);
+
+    Ok(output)
+}
+
+fn embed_webp(bytes: &[u8], metadata: &EmbedMetadata) -> io::Result<Vec<u8>> {
+    let mut chunks = bytes[12..].to_vec();
+
+    if chunks.len() < 8 {
+        return Err(invalid_data("WebP without chunks"));
+    }
+
+    if &chunks[..4] == b"VP8X" {
+        // XMP flag
+        chunks[8] |= 0x04;
+    } else {
+        let (width, height, alpha) = webp_size(&chunks)?;
+        let mut vp8x = b"VP8X\x0a\0\0\0".to_vec();
+        vp8x.extend_from_slice(&[if alpha { 0x14 } else { 0x04 }, 0, 0, 0]);
+        vp8x.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+        vp8x.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+        chunks.splice(0..0, vp8x);
+    }
+
+    let xmp = metadata.xmp();
+    chunks.extend_from_slice(b"XMP ");
+    chunks.extend_from_slice(&(xmp.len() as u32).to_le_bytes());
+    chunks.extend_from_slice(xmp.as_bytes());
+    if xmp.len() % 2 == 1 {
+        chunks.push(0);
+    }
+
+    let mut output = b"RIFF".to_vec();
+    output.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+    output.extend_from_slice(b"WEBP");
+    output.append(&mut chunks);
+
+    Ok(output)
+}
+
+/// Canvas size of a simple (VP8 / VP8L) WebP image.
+fn webp_size(chunks: &[u8]) -> io::Result<(u32, u32, bool)> {
+    let data = &chunks[8..];
+
+    match &chunks[..4] {
+        b"VP8 " if data.len() >= 10 && data[3..6] == [0x9d, 0x01, 0x2a] => Ok((
+            u16::from_le_bytes([data[6], data[7]]) as u32 & 0x3fff,
+            u16::from_le_bytes([data[8], data[9]]) as u32 & 0x3fff,
+            false,
+        )),
+        b"VP8L" if data.len() >= 5 && data[0] == 0x2f => {
+            let bits = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+            Ok((
+                (bits & 0x3fff) + 1,
+                ((bits >> 14) & 0x3fff) + 1,
+                (bits >> 28) & 1 == 1,
+            ))
+        }
+        _ => Err(invalid_data("unsupported WebP chunk")),
+    }
+}
+
+fn embed_gif(bytes: &[u8], metadata: &EmbedMetadata) -> io::Result<Vec<u8>> {
+    let Some(trailer) = bytes.iter().rposition(|byte| *byte == 0x3b) else {
+        return Err(invalid_data("GIF without trailer"));
+    };
+
+    let mut output = bytes[..trailer].to_vec();
+    output.extend_from_slice(b"\x21\xff\x0bXMP DataXMP");
+    output.extend_from_slice(metadata.xmp().as_bytes());
+    // "magic trailer" so readers skipping sub-blocks land on the terminator
+    output.push(0x01);
+    output.extend((0..=0xffu8).rev());
+    output.push(0x00);
+    output.extend_from_slice(&bytes[trailer..]);
+
+    Ok(output)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc32, embed, EmbedMetadata};
+
+    fn metadata() -> EmbedMetadata {
+        EmbedMetadata {
+            title: "title & <b>".to_string(),
+            author: "author".to_string(),
+            source_url: "https://www.pixiv.net/artworks/1".to_string(),
+            tags: vec!["tag".to_string()],
+            description: String::new(),
+        }
+    }
+
+    fn contains(bytes: &[u8], needle: &[u8]) -> bool {
+        bytes.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    }
+
+    #[test]
+    fn test_embed() {
+        let jpeg = [0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xd9];
+        let output = embed(&jpeg, &metadata()).unwrap().unwrap();
+        assert_eq!(&output[..8], &jpeg[..8]);
+        assert_eq!(&output[8..10], &[0xff, 0xe1]);
+        assert!(contains(&output, b"title &amp; &lt;b&gt;"));
+        assert!(output.ends_with(&[0xff, 0xd9]));
+
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&[0; 13 + 4]);
+        png.extend_from_slice(b"\0\0\0\0IEND\xae\x42\x60\x82");
+        let output = embed(&png, &metadata()).unwrap().unwrap();
+        assert!(contains(&output, b"iTXtXML:com.adobe.xmp"));
+        assert!(output.ends_with(b"IEND\xae\x42\x60\x82"));
+
+        let mut webp = b"RIFF\x0e\0\0\0WEBPVP8L\x05\0\0\0\x2f".to_vec();
+        webp.extend_from_slice(&(9u32 | (19 << 14)).to_le_bytes());
+        webp.push(0);
+        let output = embed(&webp, &metadata()).unwrap().unwrap();
+        assert_eq!(&output[12..16], b"VP8X");
+        assert_eq!(&output[24..30], &[9, 0, 0, 19, 0, 0]);
+        assert_eq!(
+            u32::from_le_bytes([output[4], output[5], output[6], output[7]]) as usize,
+            output.len() - 8
+        );
+
+        let output = embed(b"GIF89a\x3b", &metadata()).unwrap().unwrap();
+        assert!(contains(&output, b"XMP DataXMP"));
+        assert!(output.ends_with(&[0x01, 0x00, 0x00, 0x3b]));
+    }
+}
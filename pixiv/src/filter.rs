@@ -0,0 +1,465 @@
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::data::{ArtworksData, Content};
+
+/// Which artworks of a job are downloaded, checked after the metadata is fetched and
+/// before any image is.
+///
+/// Tags match the original name or any translation, ignoring case. Zero minimums and
+/// `max_pages` don't limit anything.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Filter {
+    /// leave out artworks marked as AI-generated
+    pub exclude_ai: bool,
+    /// artworks need these tags, empty allows every artwork
+    pub include_tags: Vec<String>,
+    /// artworks with any of these tags are left out
+    pub exclude_tags: Vec<String>,
+    /// whether artworks need any or all of `include_tags`
+    pub tags_match: TagsMatch,
+    /// the tags are regular expressions, matching anywhere in the name
+    pub tag_regex: bool,
+    /// only safe or only R-18 artworks, R-18G counts as R-18
+    pub restrict: Option<Restrict>,
+    pub min_bookmarks: usize,
+    pub min_views: usize,
+    pub max_pages: usize,
+    /// size of the first page
+    pub min_width: u32,
+    pub min_height: u32,
+    /// shape of the first page
+    pub orientation: Option<Orientation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TagsMatch {
+    #[default]
+    Any,
+    All,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Restrict {
+    Safe,
+    R18,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+    Square,
+}
+
+/// A filter with its tag patterns compiled, made once for every artwork of a job.
+#[derive(Clone, Debug)]
+pub struct CompiledFilter {
+    filter: Filter,
+    include_tags: Vec<TagPattern>,
+    exclude_tags: Vec<TagPattern>,
+}
+
+#[derive(Clone, Debug)]
+enum TagPattern {
+    /// lowercase name
+    Name(String),
+    Regex(Regex),
+}
+
+/// What is known about an artwork, listings leave some of it out.
+#[derive(Default)]
+struct Facts {
+    ai_type: Option<u8>,
+    x_restrict: Option<u8>,
+    bookmarks: Option<usize>,
+    views: Option<usize>,
+    pages: Option<usize>,
+    size: Option<(u32, u32)>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        *self == Filter::default()
+    }
+
+    /// Fails on an invalid regular expression.
+    pub fn validate(&self) -> Result<(), String> {
+        self.compile().map(|_| ())
+    }
+
+    /// Compiles the tag patterns, fails on an invalid regular expression.
+    pub fn compile(&self) -> Result<CompiledFilter, String> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| self.compile_tag(pattern))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(CompiledFilter {
+            filter: self.clone(),
+            include_tags: compile(&self.include_tags)?,
+            exclude_tags: compile(&self.exclude_tags)?,
+        })
+    }
+
+    fn compile_tag(&self, pattern: &str) -> Result<TagPattern, String> {
+        if !self.tag_regex {
+            return Ok(TagPattern::Name(pattern.to_lowercase()));
+        }
+
+        RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map(TagPattern::Regex)
+            .map_err(|err| format!("invalid tag regex {}: {}", pattern, err))
+    }
+}
+
+impl CompiledFilter {
+    /// Why the artwork is left out, `None` when it is downloaded.
+    pub fn check(&self, data: &ArtworksData) -> Option<&'static str> {
+        let tags = data
+            .tags
+            .tags
+            .iter()
+            .map(|tag| {
+                let translations = tag.translation.iter().flat_map(|names| names.values());
+
+                std::iter::once(&tag.tag)
+                    .chain(translations)
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let has_tag = |pattern: &TagPattern| tags.iter().any(|names| pattern.matches(names));
+        let included = match self.filter.tags_match {
+            TagsMatch::Any => self.include_tags.iter().any(has_tag),
+            TagsMatch::All => self.include_tags.iter().all(has_tag),
+        };
+
+        let facts = Facts {
+            ai_type: Some(data.ai_type),
+            x_restrict: Some(data.x_restrict),
+            bookmarks: Some(data.bookmark_count),
+            views: Some(data.view_count),
+            pages: Some(data.page_count),
+            size: Some((data.width, data.height)),
+        };
+
+        self.filter.check_facts(&facts).or_else(|| {
+            ((!self.include_tags.is_empty() && !included) || self.exclude_tags.iter().any(has_tag))
+                .then_some("tags")
+        })
+    }
+
+    /// Like [`CompiledFilter::check`] with an artwork of a ranking, search or bookmark
+    /// listing, so artworks can be left out before their metadata is fetched.
+    ///
+    /// Only what the listing has is checked: listed tags are the original names, so only
+    /// excluded tags are, and bookmark and view counts are left to [`CompiledFilter::check`].
+    pub fn check_listed(&self, content: &Content) -> Option<&'static str> {
+        let facts = Facts {
+            ai_type: content.ai_type,
+            x_restrict: content.x_restrict,
+            pages: (content.page_count != 0).then_some(content.page_count),
+            size: (content.width != 0 && content.height != 0)
+                .then_some((content.width, content.height)),
+            ..Default::default()
+        };
+
+        self.filter.check_facts(&facts).or_else(|| {
+            self.exclude_tags
+                .iter()
+                .any(|pattern| content.tags.iter().any(|tag| pattern.matches(&[tag])))
+                .then_some("tags")
+        })
+    }
+}
+
+impl Filter {
+    /// The first failing metadata filter, unknown facts pass.
+    fn check_facts(&self, facts: &Facts) -> Option<&'static str> {
+        let below = |value: Option<usize>, min: usize| value.is_some_and(|value| value < min);
+
+        if self.exclude_ai && facts.ai_type == Some(2) {
+            return Some("ai");
+        }
+        if let (Some(restrict), Some(x_restrict)) = (self.restrict, facts.x_restrict) {
+            if (restrict == Restrict::Safe) != (x_restrict == 0) {
+                return Some("restrict");
+            }
+        }
+        if below(facts.bookmarks, self.min_bookmarks) {
+            return Some("bookmarks");
+        }
+        if below(facts.views, self.min_views) {
+            return Some("views");
+        }
+        if self.max_pages != 0 && facts.pages.is_some_and(|pages| pages > self.max_pages) {
+            return Some("pages");
+        }
+        if let Some((width, height)) = facts.size {
+            if width < self.min_width || height < self.min_height {
+                return Some("size");
+            }
+            let shape = match width.cmp(&height) {
+                Ordering::Greater => Orientation::Landscape,
+                Ordering::Less => Orientation::Portrait,
+                Ordering::Equal => Orientation::Square,
+            };
+            if self
+                .orientation
+                .is_some_and(|orientation| orientation != shape)
+            {
+                return Some("orientation");
+            }
+        }
+
+        None
+    }
+}
+
+impl TagPattern {
+    /// Whether any of the names of one tag match.
+    fn matches(&self, names: &[&str]) -> bool {
+        match self {
+            TagPattern::Name(pattern) => names.iter().any(|name| name.to_lowercase() == *pattern),
+            TagPattern::Regex(regex) => names.iter().any(|name| regex.is_match(name)),
+        }
+    }
+}
+
+impl Display for TagsMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TagsMatch::Any => "any",
+                TagsMatch::All => "all",
+            }
+        )
+    }
+}
+
+impl Display for Restrict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Restrict::Safe => "safe",
+                Restrict::R18 => "r18",
+            }
+        )
+    }
+}
+
+impl FromStr for Restrict {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "safe" => Ok(Restrict::Safe),
+            "r18" => Ok(Restrict::R18),
+            _ => Err(format!("unknown restrict: {}", s)),
+        }
+    }
+}
+
+impl Display for Orientation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Orientation::Landscape => "landscape",
+                Orientation::Portrait => "portrait",
+                Orientation::Square => "square",
+            }
+        )
+    }
+}
+
+impl FromStr for Orientation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "landscape" => Ok(Orientation::Landscape),
+            "portrait" => Ok(Orientation::Portrait),
+            "square" => Ok(Orientation::Square),
+            _ => Err(format!("unknown orientation: {}", s)),
+        }
+    }
+}
+
+impl FromStr for TagsMatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(TagsMatch::Any),
+            "all" => Ok(TagsMatch::All),
+            _ => Err(format!("unknown tags match: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Filter, Orientation, Restrict, TagsMatch};
+    use crate::data::{ArtworksData, Content};
+
+    #[test]
+    fn test_filter() {
+        let json = r#"{"illustId":"1","title":"t","description":"","userId":"2","userName":"u",
+            "aiType":2,"xRestrict":1,"pageCount":3,"width":800,"height":600,"bookmarkCount":50,
+            "likeCount":0,"viewCount":900,"tags":{"tags":[{"tag":"a"},{"tag":"猫","translation":{"en":"Cat"}}]}}"#;
+        let data = serde_json::from_str::<ArtworksData>(json).unwrap();
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect();
+
+        assert!(Filter::default().is_empty());
+        assert_eq!(Filter::default().compile().unwrap().check(&data), None);
+        assert_eq!(
+            Filter {
+                exclude_ai: true,
+                ..Default::default()
+            }
+            .compile()
+            .unwrap()
+            .check(&data),
+            Some("ai")
+        );
+        assert_eq!(
+            Filter {
+                include_tags: tags(&["cat", "c"]),
+                ..Default::default()
+            }
+            .compile()
+            .unwrap()
+            .check(&data),
+            None
+        );
+        assert_eq!(
+            Filter {
+                include_tags: tags(&["cat", "c"]),
+                tags_match: TagsMatch::All,
+                ..Default::default()
+            }
+            .compile()
+            .unwrap()
+            .check(&data),
+            Some("tags")
+        );
+        assert_eq!(
+            Filter {
+                include_tags: tags(&["c"]),
+                ..Default::default()
+            }
+            .compile()
+            .unwrap()
+            .check(&data),
+            Some("tags")
+        );
+        assert_eq!(
+            Filter {
+                exclude_tags: tags(&["A"]),
+                ..Default::default()
+            }
+            .compile()
+            .unwrap()
+            .check(&data),
+            Some("tags")
+        );
+
+        let regex = Filter {
+            exclude_tags: tags(&["^ca"]),
+            tag_regex: true,
+            ..Default::default()
+        }
+        .compile()
+        .unwrap();
+        assert_eq!(regex.check(&data), Some("tags"));
+        let listed = |names: &[&str]| Content {
+            tags: tags(names),
+            ..Default::default()
+        };
+        assert_eq!(regex.check_listed(&listed(&["Cats"])), Some("tags"));
+        assert_eq!(regex.check_listed(&listed(&["猫"])), None);
+        let metadata = |filter: Filter| filter.compile().unwrap().check(&data);
+        assert_eq!(
+            metadata(Filter {
+                restrict: Some(Restrict::Safe),
+                ..Default::default()
+            }),
+            Some("restrict")
+        );
+        assert_eq!(
+            metadata(Filter {
+                restrict: Some(Restrict::R18),
+                min_bookmarks: 50,
+                min_views: 1000,
+                ..Default::default()
+            }),
+            Some("views")
+        );
+        assert_eq!(
+            metadata(Filter {
+                max_pages: 2,
+                ..Default::default()
+            }),
+            Some("pages")
+        );
+        assert_eq!(
+            metadata(Filter {
+                min_width: 800,
+                orientation: Some(Orientation::Portrait),
+                ..Default::default()
+            }),
+            Some("orientation")
+        );
+        assert_eq!(
+            metadata(Filter {
+                min_height: 601,
+                ..Default::default()
+            }),
+            Some("size")
+        );
+
+        // listings without a size or bookmark count only check what they have
+        let bookmarks = Filter {
+            min_bookmarks: 100,
+            min_width: 1000,
+            ..Default::default()
+        }
+        .compile()
+        .unwrap();
+        assert_eq!(bookmarks.check_listed(&listed(&[])), None);
+        assert_eq!(
+            bookmarks.check_listed(&Content {
+                width: 640,
+                height: 480,
+                ..Default::default()
+            }),
+            Some("size")
+        );
+        assert_eq!(bookmarks.check(&data), Some("bookmarks"));
+
+        assert!(Filter {
+            tag_regex: true,
+            include_tags: tags(&["("]),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
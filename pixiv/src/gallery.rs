@@ -0,0 +1,444 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use image::codecs::jpeg::JpegEncoder;
+use serde::Serialize;
+
+use crate::{archive::Archive, sidecar::find_sidecars};
+
+/// Folder of the thumbnails, inside the gallery folder.
+pub const THUMBS_DIR: &str = ".thumbs";
+/// Longest side of a thumbnail.
+const THUMB_SIZE: u32 = 320;
+
+/// Artworks of a downloaded folder, rendered as a single `index.html`.
+///
+/// Thumbnails are kept in [`THUMBS_DIR`] and only made for new or changed images, so
+/// writing the gallery again after the next download is quick.
+#[derive(Debug)]
+pub struct Gallery {
+    root: PathBuf,
+    items: BTreeMap<usize, GalleryItem>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct GalleryItem {
+    pub illust_id: usize,
+    pub title: String,
+    pub user_id: usize,
+    pub user_name: String,
+    pub tags: Vec<String>,
+    pub rank: Option<usize>,
+    /// `0` all ages, `1` R-18, `2` R-18G
+    pub x_restrict: u8,
+    /// relative to the gallery folder, `/` separated
+    pub pages: Vec<String>,
+}
+
+/// What [`Gallery::write`] did.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GallerySummary {
+    pub artworks: usize,
+    /// thumbnails made in this run
+    pub thumbnails: usize,
+    /// images that could not be read, e.g. the zip of an ugoira
+    pub failed: usize,
+}
+
+impl Gallery {
+    pub fn new<P: Into<PathBuf>>(root: P) -> io::Result<Self> {
+        Ok(Self {
+            root: fs::canonicalize(root.into())?,
+            items: BTreeMap::new(),
+        })
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &GalleryItem> {
+        self.items.values()
+    }
+
+    /// Adds the artworks of every JSON or TOML sidecar in the folder and its sub folders,
+    /// returns how many were found.
+    pub fn add_sidecars(&mut self) -> io::Result<usize> {
+        let sidecars = find_sidecars(&self.root)?;
+        let count = sidecars.len();
+
+        for (path, sidecar) in sidecars {
+            let pages = sidecar
+                .pages(&path)
+                .iter()
+                .filter_map(|page| self.relative(page))
+                .collect();
+
+            self.items.insert(
+                sidecar.illust_id,
+                GalleryItem {
+                    illust_id: sidecar.illust_id,
+                    title: sidecar.title,
+                    user_id: sidecar.user_id,
+                    user_name: sidecar.user_name,
+                    tags: sidecar.tags.into_iter().map(|tag| tag.name).collect(),
+                    rank: sidecar.rank,
+                    x_restrict: sidecar.x_restrict,
+                    pages,
+                },
+            );
+        }
+
+        Ok(count)
+    }
+
+    /// Adds the archived artworks with files in the folder that have no sidecar, returns
+    /// how many were added.
+    pub fn add_archive(&mut self, archive: &Archive) -> usize {
+        let mut count = 0;
+
+        for entry in archive.entries() {
+            if self.items.contains_key(&entry.illust_id) {
+                continue;
+            }
+            let pages = entry
+                .files
+                .iter()
+                .filter_map(|file| self.relative(&file.path))
+                .collect::<Vec<_>>();
+            if pages.is_empty() {
+                continue;
+            }
+
+            self.items.insert(
+                entry.illust_id,
+                GalleryItem {
+                    illust_id: entry.illust_id,
+                    title: entry.title.clone(),
+                    user_id: entry.user_id,
+                    user_name: entry.user_name.clone(),
+                    tags: entry.tags.clone(),
+                    rank: entry.rank,
+                    x_restrict: entry.x_restrict,
+                    pages,
+                },
+            );
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Makes the missing thumbnails with up to `threads` threads and writes `index.html`.
+    pub fn write(&self, threads: usize) -> io::Result<GallerySummary> {
+        let thumbs = self.root.join(THUMBS_DIR);
+        fs::create_dir_all(&thumbs)?;
+
+        let todo = self
+            .items
+            .values()
+            .filter_map(|item| {
+                let image = self.root.join(item.pages.first()?);
+                let thumb = thumbs.join(thumb_name(item.illust_id));
+                is_outdated(&image, &thumb).then_some((image, thumb))
+            })
+            .collect::<Vec<_>>();
+        let next = AtomicUsize::new(0);
+        let made = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..threads.max(1).min(todo.len()) {
+                scope.spawn(|| {
+                    while let Some((image, thumb)) = todo.get(next.fetch_add(1, Ordering::Relaxed))
+                    {
+                        if write_thumbnail(image, thumb).is_ok() {
+                            made.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+
+        fs::write(self.root.join("index.html"), self.html())?;
+
+        let thumbnails = made.into_inner();
+        Ok(GallerySummary {
+            artworks: self.items.len(),
+            thumbnails,
+            failed: todo.len() - thumbnails,
+        })
+    }
+
+    /// The page with the artworks, styles and script inlined.
+    pub fn html(&self) -> String {
+        let thumbs = self.root.join(THUMBS_DIR);
+        let items = self
+            .items
+            .values()
+            .map(|item| HtmlItem {
+                item,
+                thumb: thumbs
+                    .join(thumb_name(item.illust_id))
+                    .exists()
+                    .then(|| format!("{}/{}", THUMBS_DIR, thumb_name(item.illust_id))),
+            })
+            .collect::<Vec<_>>();
+        // keeps `</script>` in titles from closing the data
+        let data = serde_json::to_string(&items)
+            .unwrap()
+            .replace('<', "\\u003c");
+        let title = self
+            .root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        TEMPLATE
+            .replace("{{title}}", &escape_html(&title))
+            .replace("{{data}}", &data)
+    }
+
+    /// `path` relative to the gallery folder with `/` separators, `None` outside of it.
+    fn relative(&self, path: &Path) -> Option<String> {
+        let path = fs::canonicalize(path).ok()?;
+        let relative = path.strip_prefix(&self.root).ok()?;
+
+        Some(
+            relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct HtmlItem<'a> {
+    #[serde(flatten)]
+    item: &'a GalleryItem,
+    thumb: Option<String>,
+}
+
+fn thumb_name(illust_id: usize) -> String {
+    format!("{}.jpg", illust_id)
+}
+
+/// Whether the thumbnail is missing or older than its image.
+fn is_outdated(image: &Path, thumb: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+
+    match (modified(image), modified(thumb)) {
+        (Ok(image), Ok(thumb)) => thumb < image,
+        _ => true,
+    }
+}
+
+fn write_thumbnail(image: &Path, thumb: &Path) -> image::ImageResult<()> {
+    let small = image::open(image)?
+        .thumbnail(THUMB_SIZE, THUMB_SIZE)
+        .to_rgb8();
+    let mut file = BufWriter::new(File::create(thumb)?);
+
+    JpegEncoder::new_with_quality(&mut file, 80).encode_image(&small)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{title}}</title>
+<style>
+body { margin: 0; font-family: sans-serif; background: #f5f5f5; color: #222; }
+header { position: sticky; top: 0; padding: 12px 16px; background: #fff; box-shadow: 0 1px 4px #0002; display: flex; flex-wrap: wrap; gap: 8px; align-items: center; }
+header h1 { font-size: 18px; margin: 0 12px 0 0; }
+header input, header select { padding: 6px; font-size: 14px; }
+#selected span, .tags span { display: inline-block; margin: 2px; padding: 2px 8px; border-radius: 10px; background: #e4ecf7; cursor: pointer; font-size: 12px; }
+#selected span { background: #0096fa; color: #fff; }
+#grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(220px, 1fr)); gap: 12px; padding: 16px; }
+.card { background: #fff; border-radius: 6px; overflow: hidden; box-shadow: 0 1px 3px #0002; }
+.card .thumb { display: flex; align-items: center; justify-content: center; height: 220px; background: #eee; color: #888; text-decoration: none; }
+.card img { max-width: 100%; max-height: 220px; }
+.card .info { padding: 8px; font-size: 13px; }
+.card .info a { color: #0073c6; text-decoration: none; }
+.card .title { font-weight: bold; }
+.card .meta { color: #777; }
+</style>
+</head>
+<body>
+<header>
+<h1>{{title}}</h1>
+<input id="search" type="search" placeholder="title, author or tag">
+<select id="order">
+<option value="rank">rank</option>
+<option value="newest">newest</option>
+<option value="title">title</option>
+</select>
+<span id="count"></span>
+<span id="selected"></span>
+</header>
+<div id="grid"></div>
+<script type="application/json" id="data">{{data}}</script>
+<script>
+const items = JSON.parse(document.getElementById("data").textContent);
+const selected = new Set();
+const grid = document.getElementById("grid");
+const search = document.getElementById("search");
+const order = document.getElementById("order");
+
+function element(tag, className, text) {
+  const node = document.createElement(tag);
+  if (className) node.className = className;
+  if (text !== undefined) node.textContent = text;
+  return node;
+}
+
+function link(href, text) {
+  const node = element("a", "", text);
+  node.href = href;
+  return node;
+}
+
+function file(path) {
+  return path.split("/").map(encodeURIComponent).join("/");
+}
+
+function tag(name, onClick) {
+  const node = element("span", "", name);
+  node.onclick = onClick;
+  return node;
+}
+
+function sorted() {
+  const byRank = (a, b) => (a.rank ?? Infinity) - (b.rank ?? Infinity) || b.illust_id - a.illust_id;
+  const compare = {
+    rank: byRank,
+    newest: (a, b) => b.illust_id - a.illust_id,
+    title: (a, b) => a.title.localeCompare(b.title),
+  }[order.value];
+  return items.slice().sort(compare);
+}
+
+function matches(item) {
+  const tags = item.tags.map((tag) => tag.toLowerCase());
+  const text = search.value.trim().toLowerCase();
+  const found = !text || item.title.toLowerCase().includes(text)
+    || item.user_name.toLowerCase().includes(text) || tags.some((tag) => tag.includes(text));
+  return found && [...selected].every((tag) => tags.includes(tag.toLowerCase()));
+}
+
+function render() {
+  grid.replaceChildren();
+  const shown = sorted().filter(matches);
+  for (const item of shown) {
+    const card = element("div", "card");
+    const thumb = item.pages.length ? link(file(item.pages[0]), "") : element("div");
+    thumb.className = "thumb";
+    if (item.thumb) {
+      const image = element("img");
+      image.loading = "lazy";
+      image.src = item.thumb;
+      image.alt = item.title;
+      thumb.append(image);
+    } else {
+      thumb.textContent = "no preview";
+    }
+    const info = element("div", "info");
+    const title = element("div", "title");
+    title.append(link("https://www.pixiv.net/artworks/" + item.illust_id, item.title));
+    const user = element("div");
+    user.append(link("https://www.pixiv.net/users/" + item.user_id, item.user_name));
+    const meta = [item.pages.length + (item.pages.length === 1 ? " page" : " pages")];
+    if (item.rank) meta.unshift("#" + item.rank);
+    if (item.x_restrict) meta.push(item.x_restrict === 2 ? "R-18G" : "R-18");
+    const tags = element("div", "tags");
+    for (const name of item.tags) {
+      tags.append(tag(name, () => { selected.add(name); update(); }));
+    }
+    info.append(title, user, element("div", "meta", meta.join(" · ")), tags);
+    card.append(thumb, info);
+    grid.append(card);
+  }
+  document.getElementById("count").textContent = shown.length + " / " + items.length;
+}
+
+function update() {
+  const list = document.getElementById("selected");
+  list.replaceChildren(...[...selected].map((name) => tag(name + " ×", () => { selected.delete(name); update(); })));
+  render();
+}
+
+search.oninput = render;
+order.onchange = render;
+if (!items.some((item) => item.rank)) order.value = "newest";
+render();
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env::temp_dir,
+        fs::{create_dir_all, remove_dir_all, write},
+    };
+
+    use image::{Rgb, RgbImage};
+
+    use super::Gallery;
+    use crate::{data::ArtworksData, sidecar::Sidecar};
+
+    #[test]
+    fn test_gallery() {
+        let root = temp_dir().join(format!("x-pixiv-gallery-{}", std::process::id()));
+        let dir = root.join("user");
+        remove_dir_all(&root).ok();
+        create_dir_all(&dir).unwrap();
+
+        let json = r#"{"illustId":"1","title":"</script>","description":"","userId":"2",
+            "userName":"u","tags":{"tags":[{"tag":"cat"}]}}"#;
+        let data = serde_json::from_str::<ArtworksData>(json).unwrap();
+        // written somewhere else, then moved into the gallery folder
+        let sidecar = Sidecar {
+            rank: Some(3),
+            ..Sidecar::new(&data, vec![("url".to_string(), "old/a b-0.png".into())])
+        };
+        sidecar
+            .write(&dir, "{illust_id}", crate::sidecar::SidecarFormat::Json)
+            .unwrap();
+        RgbImage::from_pixel(640, 480, Rgb([255, 0, 0]))
+            .save(dir.join("a b-0.png"))
+            .unwrap();
+        write(dir.join("other.json"), "[]").unwrap();
+
+        let mut gallery = Gallery::new(&root).unwrap();
+        assert_eq!(gallery.add_sidecars().unwrap(), 1);
+        let item = gallery.items().next().unwrap();
+        assert_eq!(item.pages, vec!["user/a b-0.png"]);
+        assert_eq!(item.rank, Some(3));
+
+        let summary = gallery.write(2).unwrap();
+        assert_eq!((summary.artworks, summary.thumbnails), (1, 1));
+        let thumb = image::open(root.join(".thumbs/1.jpg")).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (320, 240));
+        // nothing changed
+        assert_eq!(gallery.write(2).unwrap().thumbnails, 0);
+
+        let html = std::fs::read_to_string(root.join("index.html")).unwrap();
+        remove_dir_all(&root).ok();
+
+        assert!(html.contains(r#""title":"\u003c/script>""#));
+        assert!(html.contains(r#""thumb":".thumbs/1.jpg""#));
+    }
+}
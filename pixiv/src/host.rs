@@ -0,0 +1,163 @@
+use std::{fmt::Display, str::FromStr, sync::RwLock};
+
+use serde::{Deserialize, Serialize};
+
+pub const PXIMG_HOST: &str = "i.pximg.net";
+
+static HOSTS: RwLock<Option<HostList>> = RwLock::new(None);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImageHost {
+    /// host name or base url, e.g. `pximg.example.com` or `https://example.com/pximg`
+    pub host: String,
+    /// send `referer: https://www.pixiv.net/`, pximg rejects requests without it
+    pub referer: bool,
+}
+
+/// Image hosts ordered by preference, failing hosts are moved to the end.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostList {
+    hosts: Vec<ImageHost>,
+}
+
+impl ImageHost {
+    /// The referer is sent to pximg hosts only, see [`ImageHost::from_str`] to choose.
+    pub fn new(host: &str) -> Self {
+        Self {
+            host: host.trim_end_matches('/').to_string(),
+            referer: host.contains("pximg.net"),
+        }
+    }
+
+    /// Moves an original image url onto this host.
+    pub fn rewrite(&self, url: &str) -> String {
+        let path = url
+            .split_once("://")
+            .and_then(|(_, rest)| rest.find('/').map(|index| &rest[index..]))
+            .unwrap_or(url);
+
+        if self.host.contains("://") {
+            format!("{}{}", self.host, path)
+        } else {
+            format!("https://{}{}", self.host, path)
+        }
+    }
+}
+
+impl Display for ImageHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.referer, ImageHost::new(&self.host).referer) {
+            (true, false) => write!(f, "{}#referer", self.host),
+            (false, true) => write!(f, "{}#no-referer", self.host),
+            _ => write!(f, "{}", self.host),
+        }
+    }
+}
+
+/// `HOST`, or `HOST#referer` and `HOST#no-referer` to choose whether the referer is sent.
+impl FromStr for ImageHost {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, referer) = match s.trim().rsplit_once('#') {
+            Some((host, "referer")) => (host, Some(true)),
+            Some((host, "no-referer")) => (host, Some(false)),
+            Some((_, option)) => return Err(format!("unknown image host option: {}", option)),
+            None => (s.trim(), None),
+        };
+        if host.is_empty() {
+            return Err("empty image host".to_string());
+        }
+        let image_host = ImageHost::new(host);
+
+        Ok(ImageHost {
+            referer: referer.unwrap_or(image_host.referer),
+            ..image_host
+        })
+    }
+}
+
+impl Default for HostList {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl HostList {
+    pub fn new(hosts: Vec<ImageHost>) -> Self {
+        Self {
+            hosts: if hosts.is_empty() {
+                vec![ImageHost::new(PXIMG_HOST)]
+            } else {
+                hosts
+            },
+        }
+    }
+
+    pub fn hosts(&self) -> &[ImageHost] {
+        &self.hosts
+    }
+
+    pub fn demote(&mut self, host: &ImageHost) {
+        if let Some(index) = self.hosts.iter().position(|item| item == host) {
+            let host = self.hosts.remove(index);
+            self.hosts.push(host);
+        }
+    }
+}
+
+/// Replaces the image hosts used by the downloader.
+pub fn set_image_hosts(hosts: Vec<ImageHost>) {
+    *HOSTS.write().unwrap() = Some(HostList::new(hosts));
+}
+
+pub fn image_hosts() -> Vec<ImageHost> {
+    HOSTS
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_default()
+        .hosts()
+        .to_vec()
+}
+
+pub fn demote(host: &ImageHost) {
+    HOSTS
+        .write()
+        .unwrap()
+        .get_or_insert_with(HostList::default)
+        .demote(host);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HostList, ImageHost};
+
+    #[test]
+    fn test_host_list() {
+        let url = "https://i.pximg.net/img-original/img/2023/03/23/00/05/02/106465672_p0.png";
+        let mirror = ImageHost::new("https://example.com/pximg/");
+        let mut hosts = HostList::new(vec![ImageHost::new("i.pximg.net"), mirror.clone()]);
+
+        assert!(hosts.hosts()[0].referer);
+        assert!(!mirror.referer);
+        assert_eq!(
+            mirror.rewrite(url),
+            "https://example.com/pximg/img-original/img/2023/03/23/00/05/02/106465672_p0.png"
+        );
+        assert_eq!(ImageHost::new("i.pximg.net").rewrite(url), url);
+
+        hosts.demote(&ImageHost::new("i.pximg.net"));
+        assert_eq!(hosts.hosts()[0], mirror);
+        assert_eq!(HostList::default().hosts().len(), 1);
+
+        let referer = "example.com/pximg/#referer".parse::<ImageHost>().unwrap();
+        let no_referer = "i.pximg.net#no-referer".parse::<ImageHost>().unwrap();
+        assert_eq!(referer.host, "example.com/pximg");
+        assert!(referer.referer && !no_referer.referer);
+        assert_eq!(referer.to_string(), "example.com/pximg#referer");
+        assert_eq!(ImageHost::new("i.pximg.net").to_string(), "i.pximg.net");
+        assert_eq!(no_referer.to_string().parse(), Ok(no_referer));
+        assert!("i.pximg.net#other".parse::<ImageHost>().is_err());
+    }
+}
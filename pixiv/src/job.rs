@@ -0,0 +1,1359 @@
+use std::{
+    fmt::Display,
+    fs::{metadata, remove_file},
+    io,
+    ops::Range,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        Mutex, Semaphore,
+    },
+    task::{spawn_blocking, JoinSet},
+    time::{sleep_until, Instant},
+};
+
+use crate::{
+    archive::{ArchiveEntry, ArchiveFile, SharedArchive},
+    artworks::{get_artworks_data, Quality},
+    bookmarks::Bookmarks,
+    cbz::write_cbz,
+    control::Control,
+    convert::ConvertOptions,
+    data::{ArtworksData, Content},
+    downloader::{content_length, download_with, Downloaded},
+    dupes::{self, Dhash, DupeAction, DEFAULT_DISTANCE},
+    embed::{embed_file, EmbedMetadata},
+    filter::{CompiledFilter, Filter},
+    pdf::{embeddable, write_pdf, PdfWork},
+    rank::{Rank, RankType},
+    search::{Search, SearchMode, SearchOrder},
+    series::Series,
+    sidecar::{Sidecar, SidecarFormat},
+    user::User,
+};
+
+pub type JobId = usize;
+
+/// What a job downloads.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", content = "target", rename_all = "snake_case")]
+pub enum JobKind {
+    Artwork(usize),
+    User(usize),
+    Rank {
+        rank_type: RankType,
+        range: Range<usize>,
+        /// `YYYYMMDD`, the latest ranking when not set
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        date: Option<String>,
+    },
+    Search {
+        word: String,
+        mode: SearchMode,
+        order: SearchOrder,
+        limit: usize,
+    },
+    /// public bookmarks of a user
+    Bookmarks(usize),
+    /// episodes of a manga series
+    Series(usize),
+}
+
+/// Where the files of a job go and what is written next to them.
+///
+/// `dir_name` and `file_name` are templates, `{illust_id}`, `{title}`, `{user_id}`,
+/// `{user_name}`, `{index}` and `{ext}` are replaced per file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct JobOptions {
+    pub output: PathBuf,
+    /// sub folder of `output`, empty saves into `output`
+    pub dir_name: String,
+    pub file_name: String,
+    pub sidecar: Option<SidecarFormat>,
+    pub sidecar_name: String,
+    pub embed_metadata: bool,
+    /// keep `.part` files of cancelled or failed downloads and continue them next time
+    pub resume: bool,
+    /// leave out artworks recorded in the manager's archive
+    pub skip_archived: bool,
+    pub filter: Filter,
+    pub quality: Quality,
+    /// convert the downloaded images into another format
+    pub convert: Option<ConvertOptions>,
+    /// hash the downloaded images and flag or skip near-duplicates of archived artworks
+    pub dupes: Option<DupeAction>,
+    /// Hamming distance up to which two image hashes are duplicates
+    pub dupe_distance: u32,
+    /// pack artworks with several pages and series episodes into a `.cbz` with a
+    /// `ComicInfo.xml` instead of loose pages
+    pub cbz: bool,
+    /// also write artworks with several pages and series episodes into a `.pdf` with a
+    /// title page
+    pub pdf: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Job {
+    pub kind: JobKind,
+    pub options: JobOptions,
+}
+
+/// Limits shared by every job of a manager.
+#[derive(Clone, Debug)]
+pub struct ManagerOptions {
+    /// artworks whose metadata is fetched at the same time
+    pub jobs: usize,
+    /// files downloaded at the same time
+    pub connections: usize,
+    /// minimum time between two metadata requests
+    pub rate_limit: Option<Duration>,
+    /// completely downloaded artworks are recorded here
+    pub archive: Option<SharedArchive>,
+    /// images converted at the same time
+    pub converters: usize,
+}
+
+/// Files of one artwork and where they are saved.
+#[derive(Clone, Debug)]
+pub struct ArtworkFiles {
+    pub data: ArtworksData,
+    pub dir: PathBuf,
+    pub files: Vec<(String, PathBuf)>,
+    /// position in the ranking the artwork is listed in
+    pub rank: Option<usize>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct JobSummary {
+    pub artworks: usize,
+    pub files: usize,
+    pub bytes: u64,
+    pub errors: usize,
+    pub cancelled: usize,
+    pub skipped: usize,
+    /// images that look like an archived image of another artwork
+    pub duplicates: usize,
+}
+
+/// Progress of the running jobs, published on the channel returned by [`JobManager::new`].
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JobEvent {
+    ArtworkQueued {
+        job: JobId,
+        illust_id: usize,
+    },
+    /// the artwork is left out, `archived` before fetching the metadata and reasons of
+    /// [`Filter::check`] after
+    Skipped {
+        job: JobId,
+        illust_id: usize,
+        reason: &'static str,
+    },
+    ArtworkResolved {
+        job: JobId,
+        illust_id: usize,
+        title: String,
+        user_name: String,
+        pages: usize,
+    },
+    FileStarted {
+        job: JobId,
+        illust_id: usize,
+        page: usize,
+        url: String,
+        path: PathBuf,
+    },
+    /// sent at most once per percent of the file
+    Progress {
+        job: JobId,
+        illust_id: usize,
+        page: usize,
+        bytes: u64,
+        total: u64,
+    },
+    FileFinished {
+        job: JobId,
+        illust_id: usize,
+        page: usize,
+        path: PathBuf,
+        bytes: u64,
+        sha256: String,
+    },
+    /// the image was converted, sent before the artwork is finished
+    Converted {
+        job: JobId,
+        illust_id: usize,
+        page: usize,
+        path: PathBuf,
+        bytes: u64,
+        original: PathBuf,
+        original_bytes: u64,
+        original_kept: bool,
+    },
+    /// the image looks like an image of another archived artwork, sent before the
+    /// artwork is finished
+    Duplicate {
+        job: JobId,
+        illust_id: usize,
+        page: usize,
+        path: PathBuf,
+        duplicate_of: usize,
+        duplicate_page: usize,
+        distance: u32,
+        /// the file was removed because duplicates are skipped
+        removed: bool,
+    },
+    ArtworkFinished {
+        job: JobId,
+        illust_id: usize,
+        ok: bool,
+    },
+    /// the artwork, or one page when `page` is set, was cancelled
+    Cancelled {
+        job: JobId,
+        illust_id: usize,
+        page: Option<usize>,
+    },
+    Error {
+        job: JobId,
+        illust_id: Option<usize>,
+        page: Option<usize>,
+        kind: &'static str,
+        message: String,
+    },
+    JobFinished {
+        job: JobId,
+        summary: JobSummary,
+    },
+}
+
+/// Runs jobs with shared concurrency and rate limits.
+#[derive(Clone)]
+pub struct JobManager {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    jobs: Semaphore,
+    connections: Semaphore,
+    converters: Semaphore,
+    // artworks in flight per job before the listing waits
+    max_pending: usize,
+    rate_limit: Option<RateLimit>,
+    archive: Option<SharedArchive>,
+    events: UnboundedSender<JobEvent>,
+    next_id: AtomicUsize,
+    controls: std::sync::Mutex<Vec<Control>>,
+}
+
+/// State shared by the tasks of one job.
+#[derive(Clone)]
+struct JobContext {
+    id: JobId,
+    options: Arc<JobOptions>,
+    filter: Arc<CompiledFilter>,
+    control: Control,
+}
+
+enum FileResult {
+    Done(Downloaded),
+    Failed,
+    Cancelled,
+}
+
+struct RateLimit {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+/// Artwork ids of a job, rankings, searches and bookmarks are fetched page by page.
+enum Source {
+    Ids(std::vec::IntoIter<usize>),
+    Rank(Rank),
+    Search(Search),
+    Bookmarks(Bookmarks),
+}
+
+impl Job {
+    pub fn new(kind: JobKind, options: JobOptions) -> Self {
+        Self { kind, options }
+    }
+}
+
+impl Display for JobKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobKind::Artwork(id) => write!(f, "artwork {}", id),
+            JobKind::User(id) => write!(f, "user {}", id),
+            JobKind::Rank {
+                rank_type,
+                range,
+                date,
+            } => {
+                write!(f, "rank {} {}-{}", rank_type, range.start, range.end)?;
+                match date {
+                    Some(date) => write!(f, " {}", date),
+                    None => Ok(()),
+                }
+            }
+            JobKind::Search {
+                word,
+                mode,
+                order,
+                limit,
+            } => {
+                let order = match order {
+                    SearchOrder::Newest => "newest",
+                    SearchOrder::Oldest => "oldest",
+                };
+                write!(f, "search \"{}\" {} {} {}", word, mode, order, limit)
+            }
+            JobKind::Bookmarks(id) => write!(f, "bookmarks {}", id),
+            JobKind::Series(id) => write!(f, "series {}", id),
+        }
+    }
+}
+
+impl Default for JobOptions {
+    fn default() -> Self {
+        Self {
+            output: PathBuf::from("./"),
+            dir_name: String::new(),
+            file_name: "{title}-{index}.{ext}".to_string(),
+            sidecar: None,
+            sidecar_name: "{illust_id}".to_string(),
+            embed_metadata: false,
+            resume: false,
+            skip_archived: false,
+            filter: Filter::default(),
+            quality: Quality::Original,
+            convert: None,
+            dupes: None,
+            dupe_distance: DEFAULT_DISTANCE,
+            cbz: false,
+            pdf: false,
+        }
+    }
+}
+
+impl Default for ManagerOptions {
+    fn default() -> Self {
+        Self {
+            jobs: 4,
+            connections: 8,
+            rate_limit: None,
+            archive: None,
+            converters: 2,
+        }
+    }
+}
+
+impl ArtworkFiles {
+    pub fn new(data: ArtworksData, options: &JobOptions) -> Self {
+        let dir = if options.dir_name.is_empty() {
+            options.output.clone()
+        } else {
+            options
+                .output
+                .join(fill_template(&options.dir_name, &data, 0, ""))
+        };
+        let files = data
+            .images
+            .iter()
+            .enumerate()
+            .map(|(index, url)| {
+                // the original is the fallback when the size is missing
+                let url = data
+                    .page_urls
+                    .get(index)
+                    .and_then(|urls| urls.get(options.quality.key()))
+                    .unwrap_or(url);
+                let file_name = fill_template(&options.file_name, &data, index, url);
+                (url.clone(), dir.join(file_name))
+            })
+            .collect();
+
+        Self {
+            data,
+            dir,
+            files,
+            rank: None,
+        }
+    }
+
+    /// Embeds the metadata into the files and writes the sidecar.
+    pub fn write_metadata(&self, options: &JobOptions) -> io::Result<()> {
+        if options.embed_metadata {
+            let metadata = EmbedMetadata::from(&self.data);
+
+            for (_, path) in &self.files {
+                embed_file(path, &metadata)?;
+            }
+        }
+
+        if let Some(format) = options.sidecar {
+            let sidecar = Sidecar {
+                rank: self.rank,
+                ..Sidecar::new(&self.data, self.files.clone())
+            };
+            sidecar.write(&self.dir, &options.sidecar_name, format)?;
+        }
+
+        Ok(())
+    }
+
+    /// The `.pdf` and the `.cbz` that [`ArtworkFiles::package`] writes with `options`,
+    /// single images and ugoira aren't packed.
+    pub fn package_paths(&self, options: &JobOptions) -> (Option<PathBuf>, Option<PathBuf>) {
+        let pages = self.files.iter().map(|(_, path)| path).collect::<Vec<_>>();
+        // ugoira are a zip of frames
+        let manga = self.data.illust_type != 2 && (pages.len() > 1 || self.data.series.is_some());
+        if !manga || pages.is_empty() {
+            return (None, None);
+        }
+
+        // AVIF and JPEG XL pages can't be read
+        let pdf = (options.pdf && pages.iter().all(|page| embeddable(page)))
+            .then(|| self.dir.join(package_name(&self.data, "pdf")));
+        let cbz = options
+            .cbz
+            .then(|| self.dir.join(package_name(&self.data, "cbz")));
+
+        (pdf, cbz)
+    }
+
+    /// Packs the pages as set in `options`, returns the packed files.
+    ///
+    /// Pages packed into a `.cbz` are removed, a `.pdf` alone keeps them.
+    pub fn package(&self, options: &JobOptions) -> io::Result<Vec<PathBuf>> {
+        let pages = self
+            .files
+            .iter()
+            .map(|(_, path)| path.clone())
+            .collect::<Vec<_>>();
+        let (pdf, cbz) = self.package_paths(options);
+        let mut packages = vec![];
+
+        if let Some(path) = pdf {
+            write_pdf(&path, &[PdfWork::new(&self.data, pages.clone())])?;
+            packages.push(path);
+        }
+        if let Some(path) = cbz {
+            write_cbz(&path, &self.data, &pages)?;
+            for page in &pages {
+                remove_file(page)?;
+            }
+            packages.push(path);
+        }
+
+        Ok(packages)
+    }
+}
+
+impl JobSummary {
+    pub fn add(&mut self, other: JobSummary) {
+        self.artworks += other.artworks;
+        self.files += other.files;
+        self.bytes += other.bytes;
+        self.errors += other.errors;
+        self.cancelled += other.cancelled;
+        self.skipped += other.skipped;
+        self.duplicates += other.duplicates;
+    }
+}
+
+impl RateLimit {
+    async fn wait(&self) {
+        let mut next = self.next.lock().await;
+        sleep_until(*next).await;
+        *next = Instant::now() + self.interval;
+    }
+}
+
+impl Source {
+    async fn new(kind: &JobKind) -> reqwest::Result<Self> {
+        Ok(match kind {
+            JobKind::Artwork(id) => Source::Ids(vec![*id].into_iter()),
+            JobKind::User(id) => Source::Ids(User::new(*id).get_artworks().await?.into_iter()),
+            JobKind::Rank {
+                rank_type,
+                range,
+                date,
+            } => Source::Rank(Rank::new(*rank_type, false, range.clone()).with_date(date.clone())),
+            JobKind::Search {
+                word,
+                mode,
+                order,
+                limit,
+            } => Source::Search(Search::new(word, *mode, *order, *limit)),
+            JobKind::Bookmarks(id) => Source::Bookmarks(Bookmarks::new(*id)),
+            JobKind::Series(id) => Source::Ids(Series::new(*id).get_artworks().await?.into_iter()),
+        })
+    }
+
+    /// The next artwork, with what the listing knows about it.
+    async fn next(&mut self) -> reqwest::Result<Option<Content>> {
+        Ok(match self {
+            Source::Ids(ids) => ids.next().map(|illust_id| Content {
+                illust_id,
+                ..Default::default()
+            }),
+            Source::Rank(rank) => rank.next().await?,
+            Source::Search(search) => search.next().await?,
+            Source::Bookmarks(bookmarks) => bookmarks.next().await?,
+        })
+    }
+}
+
+impl JobManager {
+    pub fn new(options: ManagerOptions) -> (Self, UnboundedReceiver<JobEvent>) {
+        let (events, receiver) = unbounded_channel();
+        let inner = Inner {
+            jobs: Semaphore::new(options.jobs.max(1)),
+            connections: Semaphore::new(options.connections.max(1)),
+            converters: Semaphore::new(options.converters.max(1)),
+            max_pending: options.jobs.max(1) * 4,
+            rate_limit: options.rate_limit.map(|interval| RateLimit {
+                interval,
+                next: Mutex::new(Instant::now()),
+            }),
+            archive: options.archive,
+            events,
+            next_id: AtomicUsize::new(0),
+            controls: std::sync::Mutex::new(vec![]),
+        };
+
+        (
+            Self {
+                inner: Arc::new(inner),
+            },
+            receiver,
+        )
+    }
+
+    /// Downloads every artwork of the job.
+    ///
+    /// Fails when the artwork list can't be fetched, failed artworks and files are
+    /// reported as [`JobEvent::Error`] and counted in the summary.
+    pub async fn run(&self, job: Job) -> reqwest::Result<JobSummary> {
+        self.run_with(job, self.job_control()).await
+    }
+
+    /// Like [`JobManager::run`], pausing or cancelling `control` pauses or cancels the job.
+    pub async fn run_with(&self, job: Job, control: Control) -> reqwest::Result<JobSummary> {
+        self.run_as(self.next_id(), job, control).await
+    }
+
+    /// Like [`JobManager::run_with`] with an id from [`JobManager::next_id`], so the
+    /// caller knows which events belong to the job before it starts.
+    pub async fn run_as(
+        &self,
+        id: JobId,
+        job: Job,
+        control: Control,
+    ) -> reqwest::Result<JobSummary> {
+        let filter = match job.options.filter.compile() {
+            Ok(filter) => filter,
+            Err(err) => {
+                // nothing would match, so the job isn't started
+                self.error(id, None, None, "filter", err);
+                let summary = JobSummary {
+                    errors: 1,
+                    ..Default::default()
+                };
+                self.send(JobEvent::JobFinished { job: id, summary });
+                return Ok(summary);
+            }
+        };
+        let context = JobContext {
+            id,
+            options: Arc::new(job.options),
+            filter: Arc::new(filter),
+            control,
+        };
+        let mut summary = JobSummary::default();
+        let mut tasks = JoinSet::new();
+        let result = match Source::new(&job.kind).await {
+            Ok(mut source) => loop {
+                // artworks that are not listed yet are dropped on cancel
+                if !context.control.proceed().await {
+                    break Ok(());
+                }
+                let (illust_id, listed) = match source.next().await {
+                    Ok(Some(listed)) => (listed.illust_id, listed),
+                    Ok(None) => break Ok(()),
+                    Err(err) => break Err(err),
+                };
+
+                if context.options.skip_archived && self.is_archived(illust_id) {
+                    self.send(JobEvent::Skipped {
+                        job: context.id,
+                        illust_id,
+                        reason: "archived",
+                    });
+                    summary.skipped += 1;
+                    continue;
+                }
+                if let Some(reason) = context.filter.check_listed(&listed) {
+                    self.send(JobEvent::Skipped {
+                        job: context.id,
+                        illust_id,
+                        reason,
+                    });
+                    summary.skipped += 1;
+                    continue;
+                }
+
+                while tasks.len() >= self.inner.max_pending {
+                    if let Some(Ok(artwork)) = tasks.join_next().await {
+                        summary.add(artwork);
+                    }
+                }
+
+                self.send(JobEvent::ArtworkQueued {
+                    job: context.id,
+                    illust_id,
+                });
+                let artwork = self
+                    .clone()
+                    .artwork(context.clone(), illust_id, listed.rank);
+                tasks.spawn(artwork);
+            },
+            Err(err) => Err(err),
+        };
+
+        while let Some(artwork) = tasks.join_next().await {
+            if let Ok(artwork) = artwork {
+                summary.add(artwork);
+            }
+        }
+
+        self.send(JobEvent::JobFinished {
+            job: context.id,
+            summary,
+        });
+
+        result.map(|_| summary)
+    }
+
+    /// A new control for one job, registered for [`JobManager::pause_all`],
+    /// [`JobManager::resume_all`] and [`JobManager::cancel_all`].
+    pub fn job_control(&self) -> Control {
+        let control = Control::new();
+        let mut controls = self.inner.controls.lock().unwrap();
+
+        controls.retain(Control::is_shared);
+        controls.push(control.clone());
+
+        control
+    }
+
+    pub fn pause_all(&self) {
+        self.controls().iter().for_each(Control::pause);
+    }
+
+    pub fn resume_all(&self) {
+        self.controls().iter().for_each(Control::resume);
+    }
+
+    /// Cancels every running job, jobs started afterwards are not affected.
+    pub fn cancel_all(&self) {
+        self.controls().iter().for_each(Control::cancel);
+    }
+
+    /// Fetches the metadata of every artwork without downloading anything,
+    /// failed artworks are reported as [`JobEvent::Error`] and left out.
+    pub async fn resolve(&self, job: &Job) -> reqwest::Result<Vec<ArtworkFiles>> {
+        let id = self.next_id();
+        let filter = match job.options.filter.compile() {
+            Ok(filter) => filter,
+            Err(err) => {
+                self.error(id, None, None, "filter", err);
+                return Ok(vec![]);
+            }
+        };
+        let mut source = Source::new(&job.kind).await?;
+        let mut listed = vec![];
+
+        while let Some(content) = source.next().await? {
+            if (job.options.skip_archived && self.is_archived(content.illust_id))
+                || filter.check_listed(&content).is_some()
+            {
+                continue;
+            }
+            listed.push(content);
+        }
+
+        // fetched at the same time, as many as the manager fetches for downloads
+        let fetched = join_all(listed.iter().map(|content| self.fetch(content.illust_id))).await;
+        let mut artworks = vec![];
+
+        for (content, data) in listed.into_iter().zip(fetched) {
+            match data {
+                Ok(data) if filter.check(&data).is_some() => {}
+                Ok(data) => artworks.push(ArtworkFiles {
+                    rank: content.rank,
+                    ..ArtworkFiles::new(data, &job.options)
+                }),
+                Err(err) => self.error(
+                    id,
+                    Some(content.illust_id),
+                    None,
+                    error_kind(&err),
+                    err.to_string(),
+                ),
+            }
+        }
+
+        Ok(artworks)
+    }
+
+    /// Sizes of images from HEAD requests, sent at the same time up to the connections
+    /// of the manager. A size is `None` when it is unknown or the request failed.
+    pub async fn content_lengths(&self, urls: &[String]) -> Vec<Option<u64>> {
+        join_all(urls.iter().map(|url| async move {
+            let _permit = self.inner.connections.acquire().await.unwrap();
+            content_length(url).await.ok().flatten()
+        }))
+        .await
+    }
+
+    fn controls(&self) -> Vec<Control> {
+        self.inner.controls.lock().unwrap().clone()
+    }
+
+    /// A new id for [`JobManager::run_as`].
+    pub fn next_id(&self) -> JobId {
+        self.inner.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn send(&self, event: JobEvent) {
+        // nobody listening is fine
+        self.inner.events.send(event).ok();
+    }
+
+    fn error(
+        &self,
+        job: JobId,
+        illust_id: Option<usize>,
+        page: Option<usize>,
+        kind: &'static str,
+        message: String,
+    ) {
+        self.send(JobEvent::Error {
+            job,
+            illust_id,
+            page,
+            kind,
+            message,
+        });
+    }
+
+    async fn fetch(&self, illust_id: usize) -> reqwest::Result<ArtworksData> {
+        let _permit = self.inner.jobs.acquire().await.unwrap();
+        if let Some(rate_limit) = &self.inner.rate_limit {
+            rate_limit.wait().await;
+        }
+
+        get_artworks_data(illust_id).await
+    }
+
+    async fn artwork(
+        self,
+        context: JobContext,
+        illust_id: usize,
+        rank: Option<usize>,
+    ) -> JobSummary {
+        let job = context.id;
+        let mut summary = JobSummary::default();
+        let data = if context.control.proceed().await {
+            Some(self.fetch(illust_id).await)
+        } else {
+            None
+        };
+        let mut artwork = match data {
+            Some(Ok(data)) => match context.filter.check(&data) {
+                Some(reason) => {
+                    self.send(JobEvent::Skipped {
+                        job,
+                        illust_id,
+                        reason,
+                    });
+                    self.send(JobEvent::ArtworkFinished {
+                        job,
+                        illust_id,
+                        ok: true,
+                    });
+                    summary.skipped += 1;
+                    return summary;
+                }
+                None => ArtworkFiles {
+                    rank,
+                    ..ArtworkFiles::new(data, &context.options)
+                },
+            },
+            Some(Err(err)) => {
+                self.error(
+                    job,
+                    Some(illust_id),
+                    None,
+                    error_kind(&err),
+                    err.to_string(),
+                );
+                self.send(JobEvent::ArtworkFinished {
+                    job,
+                    illust_id,
+                    ok: false,
+                });
+                summary.errors += 1;
+                return summary;
+            }
+            None => {
+                self.send(JobEvent::Cancelled {
+                    job,
+                    illust_id,
+                    page: None,
+                });
+                self.send(JobEvent::ArtworkFinished {
+                    job,
+                    illust_id,
+                    ok: false,
+                });
+                summary.cancelled += 1;
+                return summary;
+            }
+        };
+        let mut tasks = JoinSet::new();
+
+        summary.artworks += 1;
+        self.send(JobEvent::ArtworkResolved {
+            job,
+            illust_id,
+            title: artwork.data.title.clone(),
+            user_name: artwork.data.user_name.clone(),
+            pages: artwork.files.len(),
+        });
+
+        for (page, (url, path)) in artwork.files.iter().enumerate() {
+            let file =
+                self.clone()
+                    .file(context.clone(), illust_id, page, url.clone(), path.clone());
+            tasks.spawn(async move { (page, file.await) });
+        }
+
+        let mut archive_files = vec![];
+        while let Some(file) = tasks.join_next().await {
+            match file {
+                Ok((page, FileResult::Done(downloaded))) => {
+                    summary.files += 1;
+                    summary.bytes += downloaded.bytes;
+                    archive_files.push(ArchiveFile {
+                        page,
+                        url: artwork.files[page].0.clone(),
+                        path: downloaded.path,
+                        bytes: downloaded.bytes,
+                        sha256: downloaded.sha256,
+                        dhash: None,
+                        duplicate_of: None,
+                        conversion: None,
+                    });
+                }
+                Ok((_, FileResult::Cancelled)) => summary.cancelled += 1,
+                _ => summary.errors += 1,
+            }
+        }
+
+        // metadata of incomplete artworks is written when they are downloaded again
+        if summary.errors == 0 && summary.cancelled == 0 {
+            archive_files.sort_by_key(|file| file.page);
+            // converted images such as AVIF or JPEG XL can't be decoded for the hash
+            if context.options.dupes.is_some() {
+                hash_files(&mut archive_files).await;
+            }
+            if let Some(convert) = &context.options.convert {
+                summary.errors += self
+                    .convert_files(&context, convert, &mut artwork, &mut archive_files)
+                    .await;
+            }
+        }
+        if summary.errors == 0 && summary.cancelled == 0 {
+            if let Some(action) = context.options.dupes {
+                summary.duplicates += self
+                    .check_dupes(&context, action, &mut artwork, &mut archive_files)
+                    .await;
+            }
+            let packaged = match artwork.write_metadata(&context.options) {
+                Ok(()) => {
+                    // decoding the pages of a PDF takes a while
+                    let (files, options) = (artwork.clone(), context.options.clone());
+                    spawn_blocking(move || files.package(&options))
+                        .await
+                        .unwrap_or_else(|err| Err(io::Error::other(err)))
+                }
+                Err(err) => Err(err),
+            };
+            if let Err(err) =
+                packaged.and_then(|packages| self.archive(&artwork, archive_files, packages))
+            {
+                self.error(job, Some(illust_id), None, "io", err.to_string());
+                summary.errors += 1;
+            }
+        }
+
+        self.send(JobEvent::ArtworkFinished {
+            job,
+            illust_id,
+            ok: summary.errors == 0 && summary.cancelled == 0,
+        });
+
+        summary
+    }
+
+    /// Converts the downloaded images on the converter pool and points `artwork` and
+    /// `files` to the converted ones, returns how many failed.
+    async fn convert_files(
+        &self,
+        context: &JobContext,
+        convert: &ConvertOptions,
+        artwork: &mut ArtworkFiles,
+        files: &mut [ArchiveFile],
+    ) -> usize {
+        let illust_id = artwork.data.id;
+        let conversions = files
+            .iter()
+            .map(|file| {
+                let (path, convert) = (file.path.clone(), convert.clone());
+                async move {
+                    let _permit = self.inner.converters.acquire().await.unwrap();
+                    spawn_blocking(move || convert.convert_file(&path))
+                        .await
+                        .unwrap_or_else(|err| Err(io::Error::other(err)))
+                }
+            })
+            .collect::<Vec<_>>();
+        let converted = join_all(conversions).await;
+        let mut errors = 0;
+
+        for (file, converted) in files.iter_mut().zip(converted) {
+            match converted {
+                Ok(Some((path, conversion))) => {
+                    file.bytes = metadata(&path).map_or(0, |metadata| metadata.len());
+                    self.send(JobEvent::Converted {
+                        job: context.id,
+                        illust_id,
+                        page: file.page,
+                        path: path.clone(),
+                        bytes: file.bytes,
+                        original: conversion.original.clone(),
+                        original_bytes: conversion.original_bytes,
+                        original_kept: conversion.original_kept,
+                    });
+                    artwork.files[file.page].1 = path.clone();
+                    file.path = path;
+                    file.conversion = Some(conversion);
+                }
+                // not an image or nothing to change
+                Ok(None) => {}
+                Err(err) => {
+                    self.error(
+                        context.id,
+                        Some(illust_id),
+                        Some(file.page),
+                        "convert",
+                        err.to_string(),
+                    );
+                    errors += 1;
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Reports the hashed images that look like an archived image of another artwork,
+    /// returns how many do. Removed duplicates are dropped from `artwork`, so the metadata
+    /// and packages only list the pages left.
+    async fn check_dupes(
+        &self,
+        context: &JobContext,
+        action: DupeAction,
+        artwork: &mut ArtworkFiles,
+        files: &mut [ArchiveFile],
+    ) -> usize {
+        let illust_id = artwork.data.id;
+        let mut duplicates = 0;
+        let mut removed = vec![];
+
+        for file in files {
+            let Some(hash) = file.dhash else {
+                continue;
+            };
+            let similar = self.inner.archive.as_ref().and_then(|archive| {
+                let archive = archive.lock().unwrap();
+                dupes::find_similar(&archive, illust_id, hash, context.options.dupe_distance)
+                    .map(|(entry, similar, distance)| (entry.illust_id, similar.page, distance))
+            });
+            let Some((duplicate_of, duplicate_page, distance)) = similar else {
+                continue;
+            };
+
+            file.duplicate_of = Some(duplicate_of);
+            duplicates += 1;
+            let remove = action == DupeAction::Skip && remove_file(&file.path).is_ok();
+            if remove {
+                removed.push(file.path.clone());
+            }
+            self.send(JobEvent::Duplicate {
+                job: context.id,
+                illust_id,
+                page: file.page,
+                path: file.path.clone(),
+                duplicate_of,
+                duplicate_page,
+                distance,
+                removed: remove,
+            });
+        }
+        artwork.files.retain(|(_, path)| !removed.contains(path));
+
+        duplicates
+    }
+
+    fn is_archived(&self, illust_id: usize) -> bool {
+        self.inner
+            .archive
+            .as_ref()
+            .is_some_and(|archive| archive.lock().unwrap().contains(illust_id))
+    }
+
+    fn archive(
+        &self,
+        artwork: &ArtworkFiles,
+        files: Vec<ArchiveFile>,
+        packages: Vec<PathBuf>,
+    ) -> io::Result<()> {
+        match &self.inner.archive {
+            Some(archive) => archive.lock().unwrap().record(ArchiveEntry {
+                rank: artwork.rank,
+                packages,
+                ..ArchiveEntry::new(&artwork.data, files)
+            }),
+            None => Ok(()),
+        }
+    }
+
+    async fn file(
+        self,
+        context: JobContext,
+        illust_id: usize,
+        page: usize,
+        url: String,
+        path: PathBuf,
+    ) -> FileResult {
+        let job = context.id;
+        let _permit = self.inner.connections.acquire().await.unwrap();
+        let last_percent = AtomicUsize::new(usize::MAX);
+
+        if !context.control.proceed().await {
+            self.send(JobEvent::Cancelled {
+                job,
+                illust_id,
+                page: Some(page),
+            });
+            return FileResult::Cancelled;
+        }
+        self.send(JobEvent::FileStarted {
+            job,
+            illust_id,
+            page,
+            url: url.clone(),
+            path: path.clone(),
+        });
+
+        let result = download_with(
+            path,
+            url,
+            &context.control,
+            context.options.resume,
+            |bytes, total| {
+                let percent = (bytes * 100 / total.max(1)) as usize;
+                if last_percent.swap(percent, Ordering::Relaxed) != percent {
+                    self.send(JobEvent::Progress {
+                        job,
+                        illust_id,
+                        page,
+                        bytes,
+                        total,
+                    });
+                }
+            },
+            |_| {},
+        )
+        .await;
+
+        match result {
+            Ok(Some(downloaded)) => {
+                self.send(JobEvent::FileFinished {
+                    job,
+                    illust_id,
+                    page,
+                    path: downloaded.path.clone(),
+                    bytes: downloaded.bytes,
+                    sha256: downloaded.sha256.clone(),
+                });
+                FileResult::Done(downloaded)
+            }
+            Ok(None) => {
+                self.send(JobEvent::Cancelled {
+                    job,
+                    illust_id,
+                    page: Some(page),
+                });
+                FileResult::Cancelled
+            }
+            Err(err) => {
+                self.error(
+                    job,
+                    Some(illust_id),
+                    Some(page),
+                    err.kind(),
+                    err.to_string(),
+                );
+                FileResult::Failed
+            }
+        }
+    }
+}
+
+/// Short machine readable name of a request error.
+pub fn error_kind(err: &reqwest::Error) -> &'static str {
+    if err.is_timeout() {
+        "timeout"
+    } else if err.is_connect() {
+        "connect"
+    } else if err.is_status() {
+        "http_status"
+    } else if err.is_decode() {
+        "decode"
+    } else {
+        "request"
+    }
+}
+
+/// Replaces the template placeholders, path separators in the values become `_`.
+pub fn fill_template(template: &str, data: &ArtworksData, index: usize, url: &str) -> String {
+    template
+        .replace("{illust_id}", &data.id.to_string())
+        .replace("{title}", &clean_name(&data.title))
+        .replace("{user_id}", &data.user_id.to_string())
+        .replace("{user_name}", &clean_name(&data.user_name))
+        .replace("{index}", &index.to_string())
+        .replace("{ext}", extension(url))
+}
+
+/// A title or user name that can be put into a file name, path separators become `_`.
+pub fn clean_name(value: &str) -> String {
+    value.replace(['/', '\\'], "_")
+}
+
+/// Hashes the downloaded images, the hash stays with a file when it is converted.
+async fn hash_files(files: &mut [ArchiveFile]) {
+    for file in files {
+        let path = file.path.clone();
+        // not an image, e.g. the zip of an ugoira
+        if let Ok(Ok(hash)) = spawn_blocking(move || Dhash::open(path)).await {
+            file.dhash = Some(hash);
+        }
+    }
+}
+
+/// File name of a `.cbz` or `.pdf` of an artwork, series episodes start with the series
+/// and episode number.
+pub fn package_name(data: &ArtworksData, ext: &str) -> String {
+    match &data.series {
+        Some(series) => format!(
+            "{} {:03} {}-{}.{}",
+            clean_name(&series.title),
+            series.order,
+            clean_name(&data.title),
+            data.id,
+            ext
+        ),
+        None => format!("{}-{}.{}", clean_name(&data.title), data.id, ext),
+    }
+}
+
+fn extension(url: &str) -> &str {
+    url.rsplit_once('.').map(|(_, ext)| ext).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        env::temp_dir,
+        fs::{create_dir_all, remove_dir_all},
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    use image::{Rgb, RgbImage};
+
+    use super::{
+        fill_template, hash_files, ArtworkFiles, JobContext, JobManager, JobOptions, ManagerOptions,
+    };
+    use crate::{
+        archive::{Archive, ArchiveEntry, ArchiveFile},
+        artworks::Quality,
+        control::Control,
+        convert::{ConvertFormat, ConvertOptions},
+        data::ArtworksData,
+        dupes::{Dhash, DupeAction},
+        filter::Filter,
+    };
+
+    #[test]
+    fn test_artwork_files() {
+        let json = r#"{"illustId":"106465672","title":"a/b","description":"","userId":"45",
+            "userName":"author","tags":{"tags":[]}}"#;
+        let mut data = serde_json::from_str::<ArtworksData>(json).unwrap();
+        data.images = vec![
+            "https://i.pximg.net/img-original/img/2023/03/23/00/05/02/106465672_p0.png".to_string(),
+            "https://i.pximg.net/img-original/img/2023/03/23/00/05/02/106465672_p1.jpg".to_string(),
+        ];
+        let options = JobOptions {
+            output: PathBuf::from("images"),
+            dir_name: "{user_name}".to_string(),
+            file_name: "{title}-{illust_id}-{index}.{ext}".to_string(),
+            ..Default::default()
+        };
+        let artwork = ArtworkFiles::new(data.clone(), &options);
+        let packages = artwork.package_paths(&JobOptions {
+            cbz: true,
+            pdf: true,
+            ..Default::default()
+        });
+
+        assert_eq!(artwork.dir, PathBuf::from("images/author"));
+        assert_eq!(
+            packages,
+            (
+                Some(PathBuf::from("images/author/a_b-106465672.pdf")),
+                Some(PathBuf::from("images/author/a_b-106465672.cbz"))
+            )
+        );
+        assert_eq!(
+            artwork.files[1].1,
+            PathBuf::from("images/author/a_b-106465672-1.jpg")
+        );
+        assert_eq!(
+            ArtworkFiles::new(data.clone(), &JobOptions::default()).files[0].1,
+            PathBuf::from("./a_b-0.png")
+        );
+        assert_eq!(fill_template("{user_id}", &data, 0, ""), "45");
+
+        data.page_urls = vec![HashMap::from([(
+            "regular".to_string(),
+            "https://i.pximg.net/img-master/img/106465672_p0_master1200.jpg".to_string(),
+        )])];
+        let options = JobOptions {
+            quality: Quality::Regular,
+            ..Default::default()
+        };
+        let artwork = ArtworkFiles::new(data, &options);
+        assert_eq!(artwork.files[0].1, PathBuf::from("./a_b-0.jpg"));
+        // falls back to the original
+        assert!(artwork.files[1].0.ends_with("_p1.jpg"));
+    }
+
+    #[tokio::test]
+    async fn test_converted_dupe() {
+        let dir = temp_dir().join(format!("x-pixiv-job-dupes-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let image = RgbImage::from_fn(96, 64, |x, y| Rgb([(x * 2) as u8, (y * 3) as u8, 60]));
+        let archived = dir.join("1_p0.png");
+        image.save(&archived).unwrap();
+
+        let json = r#"{"illustId":"2","title":"copy","description":"","userId":"45",
+            "userName":"author","tags":{"tags":[]}}"#;
+        let mut data = serde_json::from_str::<ArtworksData>(json).unwrap();
+        data.images = vec!["https://i.pximg.net/img-original/img/2_p0.png".to_string()];
+        let file = |page, path: PathBuf| ArchiveFile {
+            page,
+            url: String::new(),
+            path,
+            bytes: 0,
+            sha256: String::new(),
+            dhash: None,
+            duplicate_of: None,
+            conversion: None,
+        };
+        let mut archive = Archive::open(dir.join("archive.jsonl")).unwrap();
+        archive
+            .record(ArchiveEntry {
+                illust_id: 1,
+                ..ArchiveEntry::new(
+                    &data,
+                    vec![ArchiveFile {
+                        dhash: Some(Dhash::open(&archived).unwrap()),
+                        ..file(0, archived)
+                    }],
+                )
+            })
+            .unwrap();
+
+        let (manager, _events) = JobManager::new(ManagerOptions {
+            archive: Some(Arc::new(Mutex::new(archive))),
+            ..Default::default()
+        });
+        let options = JobOptions {
+            output: dir.clone(),
+            file_name: "{illust_id}_p{index}.{ext}".to_string(),
+            dupes: Some(DupeAction::Flag),
+            ..Default::default()
+        };
+        let mut artwork = ArtworkFiles::new(data, &options);
+        image.save(&artwork.files[0].1).unwrap();
+        let mut files = vec![file(0, artwork.files[0].1.clone())];
+        let context = JobContext {
+            id: 0,
+            options: Arc::new(options),
+            filter: Arc::new(Filter::default().compile().unwrap()),
+            control: Control::new(),
+        };
+        let convert = ConvertOptions {
+            format: ConvertFormat::Avif,
+            quality: 80,
+            max_size: None,
+            keep_original: false,
+        };
+
+        hash_files(&mut files).await;
+        let errors = manager
+            .convert_files(&context, &convert, &mut artwork, &mut files)
+            .await;
+        let duplicates = manager
+            .check_dupes(&context, DupeAction::Flag, &mut artwork, &mut files)
+            .await;
+        remove_dir_all(&dir).unwrap();
+
+        assert_eq!(errors, 0);
+        // the AVIF can't be decoded, the hash of the PNG is kept
+        assert_eq!(files[0].path, dir.join("2_p0.avif"));
+        assert!(files[0].dhash.is_some());
+        assert_eq!(duplicates, 1);
+        assert_eq!(files[0].duplicate_of, Some(1));
+    }
+}
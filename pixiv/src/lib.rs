@@ -1,6 +1,26 @@
+pub mod archive;
 pub mod artworks;
+pub mod bookmarks;
+pub mod cbz;
+pub mod client;
+pub mod control;
+pub mod convert;
 pub mod data;
 pub mod downloader;
+pub mod dupes;
+pub mod embed;
+pub mod filter;
+pub mod gallery;
+pub mod host;
+pub mod job;
+pub mod pdf;
+pub mod queue;
 pub mod rank;
+pub mod schedule;
+pub mod search;
+pub mod series;
+pub mod sidecar;
+pub mod sync;
+pub mod target;
 pub mod user;
 pub use reqwest::{Error, Result};
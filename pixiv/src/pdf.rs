@@ -0,0 +1,771 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, rename, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::ZlibEncoder, Compression};
+use image::DynamicImage;
+
+use crate::{
+    data::ArtworksData,
+    job::clean_name,
+    sidecar::{find_sidecars, Sidecar, SidecarSeries},
+};
+
+/// Size of the title page when the first page can't be read, A4 in points.
+const TITLE_SIZE: (f32, f32) = (595.0, 842.0);
+
+/// An artwork in a PDF, a series is one work per episode.
+#[derive(Clone, Debug)]
+pub struct PdfWork {
+    pub illust_id: usize,
+    pub title: String,
+    pub user_id: usize,
+    pub user_name: String,
+    pub tags: Vec<String>,
+    pub create_date: String,
+    pub series: Option<SidecarSeries>,
+    pub pages: Vec<PathBuf>,
+}
+
+impl PdfWork {
+    pub fn new(data: &ArtworksData, pages: Vec<PathBuf>) -> Self {
+        Self {
+            illust_id: data.id,
+            title: data.title.clone(),
+            user_id: data.user_id,
+            user_name: data.user_name.clone(),
+            tags: data.tags.tags.iter().map(|tag| tag.tag.clone()).collect(),
+            create_date: data.create_date.clone(),
+            series: data.series.as_ref().map(|series| SidecarSeries {
+                series_id: series.series_id,
+                title: series.title.clone(),
+                order: series.order,
+            }),
+            pages,
+        }
+    }
+
+    pub fn from_sidecar(sidecar: &Sidecar, pages: Vec<PathBuf>) -> Self {
+        Self {
+            illust_id: sidecar.illust_id,
+            title: sidecar.title.clone(),
+            user_id: sidecar.user_id,
+            user_name: sidecar.user_name.clone(),
+            tags: sidecar.tags.iter().map(|tag| tag.name.clone()).collect(),
+            create_date: sidecar.create_date.clone(),
+            series: sidecar.series.clone(),
+            pages,
+        }
+    }
+}
+
+/// A PDF of a downloaded folder and the artworks in it.
+#[derive(Clone, Debug)]
+pub struct FolderPdf {
+    pub path: PathBuf,
+    pub works: Vec<PdfWork>,
+}
+
+impl FolderPdf {
+    /// Whether the PDF is missing or older than one of its pages.
+    pub fn outdated(&self) -> bool {
+        let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        let Some(written) = modified(&self.path) else {
+            return true;
+        };
+
+        self.works
+            .iter()
+            .flat_map(|work| &work.pages)
+            .any(|page| modified(page).is_none_or(|page| page > written))
+    }
+}
+
+/// The PDFs of the artworks with sidecars under `dir`: one per series with the episodes
+/// in order, next to the first episode, and one per other artwork with several pages.
+pub fn folder_pdfs(dir: &Path) -> io::Result<Vec<FolderPdf>> {
+    // episodes with the folder of their sidecar
+    let mut series = BTreeMap::<usize, Vec<(PathBuf, PdfWork)>>::new();
+    let mut pdfs = vec![];
+
+    for (path, sidecar) in find_sidecars(dir)? {
+        // ugoira are a zip of frames, packed artworks have no pages left
+        let pages = sidecar
+            .pages(&path)
+            .into_iter()
+            .filter(|page| embeddable(page))
+            .collect::<Vec<_>>();
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let work = PdfWork::from_sidecar(&sidecar, pages);
+
+        match &sidecar.series {
+            _ if work.pages.is_empty() => {}
+            Some(info) => series.entry(info.series_id).or_default().push((dir, work)),
+            None if work.pages.len() > 1 => pdfs.push(FolderPdf {
+                path: dir.join(format!(
+                    "{}-{}.pdf",
+                    clean_name(&work.title),
+                    work.illust_id
+                )),
+                works: vec![work],
+            }),
+            None => {}
+        }
+    }
+    for (series_id, mut episodes) in series {
+        episodes.sort_by_key(|(_, work)| work.series.as_ref().map(|series| series.order));
+        let (dir, first) = &episodes[0];
+        let title = &first.series.as_ref().unwrap().title;
+        pdfs.push(FolderPdf {
+            path: dir.join(format!("{}-s{}.pdf", clean_name(title), series_id)),
+            works: episodes.into_iter().map(|(_, work)| work).collect(),
+        });
+    }
+
+    Ok(pdfs)
+}
+
+/// Writes `works` in order into the PDF at `path`: a title page, then the pages of every
+/// work with a bookmark on its first page.
+///
+/// Pages keep their pixel size, one pixel per point. JPEGs are embedded as they are and
+/// other images are decoded and stored losslessly, with their transparency.
+pub fn write_pdf(path: &Path, works: &[PdfWork]) -> io::Result<()> {
+    let first = works.first().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "no artworks to write a PDF of")
+    })?;
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let mut pdf = PdfWriter::new(BufWriter::new(File::create(&part)?))?;
+
+    let size = first
+        .pages
+        .first()
+        .and_then(|page| image::image_dimensions(page).ok())
+        .map(|(width, height)| (width as f32, height as f32))
+        .unwrap_or(TITLE_SIZE);
+    pdf.text_page(size, &title_lines(works))?;
+    for work in works {
+        let mut bookmark = true;
+        for page in &work.pages {
+            let id = pdf.image_page(page)?;
+            if bookmark {
+                let title = match &work.series {
+                    Some(series) => format!("#{} {}", series.order, work.title),
+                    None => work.title.clone(),
+                };
+                pdf.bookmark(title, id);
+                bookmark = false;
+            }
+        }
+    }
+
+    let (title, author) = match &first.series {
+        Some(series) => (&series.title, &first.user_name),
+        None => (&first.title, &first.user_name),
+    };
+    let mut tags = Vec::<&str>::new();
+    for tag in works.iter().flat_map(|work| &work.tags) {
+        if !tags.contains(&tag.as_str()) {
+            tags.push(tag);
+        }
+    }
+    pdf.finish(&[
+        ("Title", title.clone()),
+        ("Author", author.clone()),
+        ("Keywords", tags.join(", ")),
+        ("Creator", "x-pixiv".to_string()),
+    ])?
+    .flush()?;
+
+    rename(&part, path)
+}
+
+/// Lines of the title page with their font size relative to the page width.
+fn title_lines(works: &[PdfWork]) -> Vec<(f32, String)> {
+    let first = &works[0];
+    let mut lines = vec![];
+
+    match &first.series {
+        Some(series) => {
+            lines.push((1.0 / 16.0, series.title.clone()));
+            lines.push((1.0 / 32.0, first.user_name.clone()));
+            let episodes = match works {
+                [work] => format!("#{}", work.series.as_ref().unwrap().order),
+                _ => format!("{} episodes", works.len()),
+            };
+            lines.push((1.0 / 40.0, episodes));
+            lines.push((
+                1.0 / 48.0,
+                format!(
+                    "https://www.pixiv.net/user/{}/series/{}",
+                    first.user_id, series.series_id
+                ),
+            ));
+        }
+        None => {
+            lines.push((1.0 / 16.0, first.title.clone()));
+            lines.push((1.0 / 32.0, first.user_name.clone()));
+            // e.g. 2023-03-20T15:00:00+00:00
+            lines.push((
+                1.0 / 40.0,
+                first.create_date.get(..10).unwrap_or_default().to_string(),
+            ));
+            lines.push((
+                1.0 / 48.0,
+                format!("https://www.pixiv.net/artworks/{}", first.illust_id),
+            ));
+        }
+    }
+    if !first.tags.is_empty() {
+        lines.push((1.0 / 48.0, first.tags.join("  ")));
+    }
+
+    lines
+}
+
+/// A minimal PDF 1.5 writer, objects are written as they come and the page tree, the
+/// outline and the cross-reference table at the end.
+struct PdfWriter<W: Write> {
+    out: W,
+    position: usize,
+    /// byte offset of every object, object `n` is at `n - 1`
+    offsets: Vec<usize>,
+    pages: Vec<usize>,
+    bookmarks: Vec<(String, usize)>,
+    font: Option<usize>,
+}
+
+/// Catalog and page tree are written last but referenced by every page.
+const CATALOG: usize = 1;
+const PAGES: usize = 2;
+
+struct Raster {
+    bits: u8,
+    color_space: &'static str,
+    data: Vec<u8>,
+}
+
+impl<W: Write> PdfWriter<W> {
+    fn new(out: W) -> io::Result<Self> {
+        let mut pdf = Self {
+            out,
+            position: 0,
+            offsets: vec![0, 0],
+            pages: vec![],
+            bookmarks: vec![],
+            font: None,
+        };
+        // the binary comment marks the file as binary for transfer tools
+        pdf.write(b"%PDF-1.5\n%\xe2\xe3\xcf\xd3\n")?;
+
+        Ok(pdf)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.position += bytes.len();
+
+        Ok(())
+    }
+
+    fn reserve(&mut self) -> usize {
+        self.offsets.push(0);
+        self.offsets.len()
+    }
+
+    fn object(&mut self, id: usize, body: &str) -> io::Result<()> {
+        self.offsets[id - 1] = self.position;
+        self.write(format!("{} 0 obj\n{}\nendobj\n", id, body).as_bytes())
+    }
+
+    fn stream(&mut self, id: usize, dict: &str, data: &[u8]) -> io::Result<()> {
+        self.offsets[id - 1] = self.position;
+        let dict = format!("{} /Length {}", dict, data.len());
+        self.write(format!("{} 0 obj\n<< {} >>\nstream\n", id, dict.trim_start()).as_bytes())?;
+        self.write(data)?;
+        self.write(b"\nendstream\nendobj\n")
+    }
+
+    fn page(
+        &mut self,
+        (width, height): (f32, f32),
+        resources: &str,
+        content: &str,
+    ) -> io::Result<usize> {
+        let content_id = self.reserve();
+        self.stream(content_id, "", content.as_bytes())?;
+        let id = self.reserve();
+        self.object(
+            id,
+            &format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << {} >> /Contents {} 0 R >>",
+                PAGES, width, height, resources, content_id
+            ),
+        )?;
+        self.pages.push(id);
+
+        Ok(id)
+    }
+
+    /// Adds a page the size of the image at `path`, returns the id of the page.
+    fn image_page(&mut self, path: &Path) -> io::Result<usize> {
+        let bytes = fs::read(path)?;
+        let id = self.reserve();
+
+        let (width, height) = match jpeg_info(&bytes) {
+            Some((width, height, components)) => {
+                let color_space = match components {
+                    1 => "/DeviceGray",
+                    // Adobe writes CMYK JPEGs inverted
+                    4 => "/DeviceCMYK /Decode [1 0 1 0 1 0 1 0]",
+                    _ => "/DeviceRGB",
+                };
+                self.stream(
+                    id,
+                    &format!(
+                        "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent 8 /Filter /DCTDecode",
+                        width, height, color_space
+                    ),
+                    &bytes,
+                )?;
+                (width, height)
+            }
+            None => {
+                let image = image::load_from_memory(&bytes)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                let (width, height) = (image.width(), image.height());
+                let (color, alpha) = raster(image);
+                let mut smask = String::new();
+                if let Some(alpha) = alpha {
+                    let alpha_id = self.reserve();
+                    self.raster(alpha_id, width, height, &alpha, "")?;
+                    smask = format!(" /SMask {} 0 R", alpha_id);
+                }
+                self.raster(id, width, height, &color, &smask)?;
+                (width, height)
+            }
+        };
+
+        self.page(
+            (width as f32, height as f32),
+            &format!("/XObject << /Im0 {} 0 R >>", id),
+            &format!("q {} 0 0 {} 0 0 cm /Im0 Do Q", width, height),
+        )
+    }
+
+    fn raster(
+        &mut self,
+        id: usize,
+        width: u32,
+        height: u32,
+        raster: &Raster,
+        extra: &str,
+    ) -> io::Result<()> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(&raster.data)?;
+        let data = encoder.finish()?;
+
+        self.stream(
+            id,
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent {} /Filter /FlateDecode{}",
+                width, height, raster.color_space, raster.bits, extra
+            ),
+            &data,
+        )
+    }
+
+    /// Adds a page with `lines` of text, sizes are relative to the page width.
+    ///
+    /// The font is a standard Japanese font readers bring themselves, so text isn't
+    /// embedded as outlines and titles in any script stay searchable.
+    fn text_page(&mut self, size: (f32, f32), lines: &[(f32, String)]) -> io::Result<usize> {
+        let font = match self.font {
+            Some(font) => font,
+            None => self.font()?,
+        };
+        let (width, height) = size;
+        let margin = width / 12.0;
+        let mut y = height - margin * 2.0;
+        let mut content = String::new();
+
+        for (scale, line) in lines {
+            let size = width * scale;
+            for line in wrap(line, (width - margin * 2.0) / size) {
+                y -= size * 1.4;
+                content.push_str(&format!(
+                    "BT /F0 {:.2} Tf {:.2} {:.2} Td <{}> Tj ET\n",
+                    size,
+                    margin,
+                    y,
+                    ucs2_hex(&line)
+                ));
+            }
+            y -= size * 0.6;
+        }
+
+        self.page(size, &format!("/Font << /F0 {} 0 R >>", font), &content)
+    }
+
+    fn font(&mut self) -> io::Result<usize> {
+        let descriptor = self.reserve();
+        self.object(
+            descriptor,
+            "<< /Type /FontDescriptor /FontName /KozGoPr6N-Medium /Flags 4 /FontBBox [-538 -374 1254 1418] /ItalicAngle 0 /Ascent 880 /Descent -120 /CapHeight 763 /StemV 99 >>",
+        )?;
+        let cid_font = self.reserve();
+        self.object(
+            cid_font,
+            &format!(
+                "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /KozGoPr6N-Medium /CIDSystemInfo << /Registry (Adobe) /Ordering (Japan1) /Supplement 6 >> /FontDescriptor {} 0 R /DW 1000 /W [231 325 500] >>",
+                descriptor
+            ),
+        )?;
+        let font = self.reserve();
+        self.object(
+            font,
+            &format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /KozGoPr6N-Medium-UniJIS-UCS2-HW-H /Encoding /UniJIS-UCS2-HW-H /DescendantFonts [{} 0 R] >>",
+                cid_font
+            ),
+        )?;
+        self.font = Some(font);
+
+        Ok(font)
+    }
+
+    fn bookmark(&mut self, title: String, page: usize) {
+        self.bookmarks.push((title, page));
+    }
+
+    /// Writes the page tree, the outline, the `info` dictionary and the cross-reference
+    /// table, returns the underlying writer.
+    fn finish(mut self, info: &[(&str, String)]) -> io::Result<W> {
+        let kids = self
+            .pages
+            .iter()
+            .map(|id| format!("{} 0 R", id))
+            .collect::<Vec<_>>();
+        self.object(
+            PAGES,
+            &format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                kids.len()
+            ),
+        )?;
+
+        let outlines = self.reserve();
+        let bookmarks = std::mem::take(&mut self.bookmarks);
+        let ids = bookmarks.iter().map(|_| self.reserve()).collect::<Vec<_>>();
+        for (index, (title, page)) in bookmarks.iter().enumerate() {
+            let mut item = format!(
+                "<< /Title <{}> /Parent {} 0 R /Dest [{} 0 R /Fit]",
+                utf16_hex(title),
+                outlines,
+                page
+            );
+            if index > 0 {
+                item.push_str(&format!(" /Prev {} 0 R", ids[index - 1]));
+            }
+            if let Some(next) = ids.get(index + 1) {
+                item.push_str(&format!(" /Next {} 0 R", next));
+            }
+            item.push_str(" >>");
+            self.object(ids[index], &item)?;
+        }
+        match (ids.first(), ids.last()) {
+            (Some(first), Some(last)) => self.object(
+                outlines,
+                &format!(
+                    "<< /Type /Outlines /First {} 0 R /Last {} 0 R /Count {} >>",
+                    first,
+                    last,
+                    ids.len()
+                ),
+            )?,
+            _ => self.object(outlines, "<< /Type /Outlines /Count 0 >>")?,
+        }
+        self.object(
+            CATALOG,
+            &format!(
+                "<< /Type /Catalog /Pages {} 0 R /Outlines {} 0 R /PageMode /UseOutlines >>",
+                PAGES, outlines
+            ),
+        )?;
+
+        let info_id = self.reserve();
+        let entries = info
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| format!("/{} <{}>", key, utf16_hex(value)))
+            .collect::<Vec<_>>();
+        self.object(info_id, &format!("<< {} >>", entries.join(" ")))?;
+
+        let xref = self.position;
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1,
+            CATALOG,
+            info_id,
+            xref
+        ));
+        self.write(table.as_bytes())?;
+
+        Ok(self.out)
+    }
+}
+
+/// Width, height and number of components of a baseline or progressive JPEG.
+fn jpeg_info(bytes: &[u8]) -> Option<(u32, u32, u8)> {
+    if bytes.get(..2)? != [0xff, 0xd8] {
+        return None;
+    }
+    let mut index = 2;
+
+    loop {
+        if *bytes.get(index)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(index + 1)?;
+        // fill bytes before a marker
+        if marker == 0xff {
+            index += 1;
+            continue;
+        }
+        let length = u16::from_be_bytes([*bytes.get(index + 2)?, *bytes.get(index + 3)?]) as usize;
+        // start of frame, except DHT, JPG and DAC which share the range
+        if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+            let frame = bytes.get(index + 4..index + 10)?;
+            let height = u16::from_be_bytes([frame[1], frame[2]]) as u32;
+            let width = u16::from_be_bytes([frame[3], frame[4]]) as u32;
+            return Some((width, height, frame[5]));
+        }
+        index += 2 + length;
+    }
+}
+
+/// Samples of the color and the alpha channel, 16 bit images stay 16 bit.
+fn raster(image: DynamicImage) -> (Raster, Option<Raster>) {
+    let color = image.color();
+    let gray = !color.has_color();
+    let wide = color.bits_per_pixel() / color.channel_count() as u16 > 8;
+    let channels = if gray { 1 } else { 3 };
+    let bytes = if wide { 2 } else { 1 };
+
+    let samples = match (gray, wide) {
+        (true, false) => image.to_luma_alpha8().into_raw(),
+        (false, false) => image.to_rgba8().into_raw(),
+        (true, true) => to_be_bytes(image.to_luma_alpha16().into_raw()),
+        (false, true) => to_be_bytes(image.to_rgba16().into_raw()),
+    };
+    let mut color_data = vec![];
+    let mut alpha_data = vec![];
+    for pixel in samples.chunks_exact((channels + 1) * bytes) {
+        color_data.extend_from_slice(&pixel[..channels * bytes]);
+        alpha_data.extend_from_slice(&pixel[channels * bytes..]);
+    }
+
+    let raster = |color_space, data| Raster {
+        bits: bytes as u8 * 8,
+        color_space,
+        data,
+    };
+    let alpha = color.has_alpha().then(|| raster("/DeviceGray", alpha_data));
+
+    (
+        raster(if gray { "/DeviceGray" } else { "/DeviceRGB" }, color_data),
+        alpha,
+    )
+}
+
+fn to_be_bytes(samples: Vec<u16>) -> Vec<u8> {
+    samples
+        .into_iter()
+        .flat_map(|sample| sample.to_be_bytes())
+        .collect()
+}
+
+/// Whether the image at `path` can be a page, AVIF and JPEG XL images can't be read yet.
+pub fn embeddable(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    ["jpg", "jpeg", "png", "gif", "webp"].contains(&ext.as_str())
+}
+
+/// Breaks `text` into lines of at most `width` em, half-width characters are half an em.
+fn wrap(text: &str, width: f32) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut used = 0.0;
+
+    for c in text.chars() {
+        let advance = if c.is_ascii() { 0.5 } else { 1.0 };
+        if used + advance > width && !line.is_empty() {
+            lines.push(line.trim_end().to_string());
+            line.clear();
+            used = 0.0;
+        }
+        line.push(c);
+        used += advance;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// UCS-2 code units for the text font, characters outside it become `?`.
+fn ucs2_hex(text: &str) -> String {
+    text.chars()
+        .map(|c| match u16::try_from(c as u32) {
+            Ok(unit) => format!("{:04X}", unit),
+            Err(_) => "003F".to_string(),
+        })
+        .collect()
+}
+
+/// A UTF-16 text string, e.g. for the document info and bookmarks.
+fn utf16_hex(text: &str) -> String {
+    let mut hex = String::from("FEFF");
+    for unit in text.encode_utf16() {
+        hex.push_str(&format!("{:04X}", unit));
+    }
+
+    hex
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env::temp_dir,
+        fs::{create_dir_all, read, remove_dir_all},
+    };
+
+    use image::{GrayImage, ImageBuffer, Luma, LumaA, Rgb, RgbImage};
+
+    use super::{folder_pdfs, jpeg_info, write_pdf, PdfWork};
+    use crate::{
+        data::ArtworksData,
+        sidecar::{Sidecar, SidecarFormat, SidecarSeries},
+    };
+
+    fn count(haystack: &[u8], needle: &str) -> usize {
+        haystack
+            .windows(needle.len())
+            .filter(|window| *window == needle.as_bytes())
+            .count()
+    }
+
+    #[test]
+    fn test_pdf() {
+        let dir = temp_dir().join(format!("x-pixiv-pdf-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let jpeg = dir.join("1.jpg");
+        RgbImage::from_pixel(30, 20, Rgb([200, 10, 10]))
+            .save(&jpeg)
+            .unwrap();
+        let png = dir.join("2.png");
+        ImageBuffer::<LumaA<u16>, _>::from_pixel(10, 40, LumaA([1000, 30000]))
+            .save(&png)
+            .unwrap();
+        let gray = dir.join("3.png");
+        GrayImage::from_pixel(5, 5, Luma([9])).save(&gray).unwrap();
+
+        let work = |illust_id, order, pages| PdfWork {
+            illust_id,
+            title: format!("第{}話", order),
+            user_id: 2,
+            user_name: "u".to_string(),
+            tags: vec!["漫画".to_string()],
+            create_date: "2023-03-20T15:00:00+00:00".to_string(),
+            series: Some(SidecarSeries {
+                series_id: 77,
+                title: "S".to_string(),
+                order,
+            }),
+            pages,
+        };
+        let path = dir.join("S.pdf");
+        write_pdf(
+            &path,
+            &[
+                work(10, 1, vec![jpeg.clone(), png]),
+                work(11, 2, vec![gray]),
+            ],
+        )
+        .unwrap();
+        let pdf = read(&path).unwrap();
+        let jpeg = read(&jpeg).unwrap();
+        remove_dir_all(&dir).ok();
+
+        assert_eq!(jpeg_info(&jpeg), Some((30, 20, 3)));
+        assert!(pdf.starts_with(b"%PDF-1.5"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        // the title page and three images
+        assert_eq!(count(&pdf, "/Type /Page "), 4);
+        assert_eq!(count(&pdf, "/DCTDecode"), 1);
+        assert!(pdf.windows(jpeg.len()).any(|window| window == jpeg));
+        assert_eq!(count(&pdf, "/BitsPerComponent 16"), 2);
+        assert_eq!(count(&pdf, "/SMask"), 1);
+        // a bookmark per episode
+        assert_eq!(count(&pdf, "/Fit]"), 2);
+
+        // every object is where the cross-reference table says
+        let xref = pdf
+            .windows(6)
+            .rposition(|window| window == b"\nxref\n")
+            .unwrap();
+        let table = std::str::from_utf8(&pdf[xref + 1..]).unwrap();
+        let entries = table
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "));
+        for (index, entry) in entries.enumerate() {
+            let offset = entry[..10].parse::<usize>().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", index + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_folder_pdfs() {
+        let dir = temp_dir().join(format!("x-pixiv-folder-pdfs-{}", std::process::id()));
+        // the first episode is in the second folder
+        for (folder, illust_id, order) in [("a", 12, 2), ("b", 11, 1), ("c", 13, 3)] {
+            let folder = dir.join(folder);
+            create_dir_all(&folder).unwrap();
+            let page = folder.join(format!("{}_p0.png", illust_id));
+            GrayImage::from_pixel(4, 4, Luma([9])).save(&page).unwrap();
+            let json = format!(
+                r#"{{"illustId":"{}","title":"t","description":"","userId":"2","userName":"u",
+                "tags":{{"tags":[]}},"seriesNavData":{{"seriesId":"77","title":"s/1","order":{}}}}}"#,
+                illust_id, order
+            );
+            let data = serde_json::from_str::<ArtworksData>(&json).unwrap();
+            Sidecar::new(&data, vec![(String::new(), page)])
+                .write(&folder, "{illust_id}", SidecarFormat::Json)
+                .unwrap();
+        }
+
+        let pdfs = folder_pdfs(&dir).unwrap();
+        remove_dir_all(&dir).unwrap();
+
+        assert_eq!(pdfs.len(), 1);
+        assert_eq!(pdfs[0].path, dir.join("b").join("s_1-s77.pdf"));
+        let ids = pdfs[0].works.iter().map(|work| work.illust_id);
+        assert_eq!(ids.collect::<Vec<_>>(), vec![11, 12, 13]);
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    fs::{read_to_string, rename, write},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::job::{Job, JobSummary};
+
+/// Jobs waiting to be downloaded, saved between runs.
+///
+/// Entries stay in the queue until their job finishes, so jobs that were running
+/// when the process exited are picked up again.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct JobQueue {
+    #[serde(default)]
+    next_id: usize,
+    #[serde(default)]
+    pub entries: Vec<QueueEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QueueEntry {
+    pub id: usize,
+    pub job: Job,
+    /// finished runs that had failures
+    #[serde(default)]
+    pub attempts: usize,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+impl JobQueue {
+    /// Reads the queue, a missing file is an empty queue.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match read_to_string(path) {
+            Ok(content) => {
+                toml::from_str(&content).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Writes the queue through a temporary file, so a crash never leaves half a queue.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let content =
+            toml::to_string(self).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        let temp = path.with_extension("tmp");
+
+        write(&temp, content)?;
+        rename(temp, path)
+    }
+
+    /// Appends a job and returns its entry id.
+    pub fn push(&mut self, job: Job) -> usize {
+        self.next_id += 1;
+        self.entries.push(QueueEntry {
+            id: self.next_id,
+            job,
+            attempts: 0,
+            last_error: None,
+        });
+
+        self.next_id
+    }
+
+    pub fn get(&self, id: usize) -> Option<&QueueEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<QueueEntry> {
+        let index = self.entries.iter().position(|entry| entry.id == id)?;
+
+        Some(self.entries.remove(index))
+    }
+
+    /// Keeps the entry for another run and records why this one failed.
+    pub fn fail(&mut self, id: usize, error: String) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == id) {
+            entry.attempts += 1;
+            entry.last_error = Some(error);
+        }
+    }
+}
+
+/// A queue file that the tasks of one process update together.
+///
+/// Every update reads the file again, so entries added by other processes are kept.
+#[derive(Clone, Debug)]
+pub struct QueueFile {
+    path: PathBuf,
+    lock: Arc<Mutex<()>>,
+}
+
+impl QueueFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            lock: Arc::default(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Applies `f` to the queue and saves it.
+    ///
+    /// A queue that can't be read is left as it is and `f` isn't applied, so the jobs in a
+    /// broken file aren't overwritten.
+    pub fn update<T>(&self, f: impl FnOnce(&mut JobQueue) -> T) -> io::Result<T> {
+        let _lock = self.lock.lock().unwrap();
+        let mut queue = JobQueue::load(&self.path)?;
+        let result = f(&mut queue);
+
+        queue.save(&self.path)?;
+        Ok(result)
+    }
+
+    /// Removes a finished entry. Failed entries stay queued with their error, interrupted
+    /// ones with cancelled artworks or files stay unchanged to run again.
+    pub fn finish(&self, id: usize, result: &reqwest::Result<JobSummary>) -> io::Result<()> {
+        self.update(|queue| match result {
+            Ok(summary) if summary.errors == 0 && summary.cancelled == 0 => {
+                queue.remove(id);
+            }
+            Ok(summary) if summary.cancelled > 0 => {}
+            Ok(summary) => {
+                queue.fail(id, format!("{} artworks or files failed", summary.errors));
+            }
+            Err(err) => queue.fail(id, err.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env::temp_dir, fs};
+
+    use super::{JobQueue, QueueFile};
+    use crate::{
+        job::{Job, JobKind, JobOptions, JobSummary},
+        rank::RankType,
+        search::{SearchMode, SearchOrder},
+        sidecar::SidecarFormat,
+    };
+
+    #[test]
+    fn test_queue() {
+        let mut queue = JobQueue::default();
+        let options = JobOptions {
+            sidecar: Some(SidecarFormat::Json),
+            ..Default::default()
+        };
+
+        let artwork = queue.push(Job::new(JobKind::Artwork(1), options.clone()));
+        let rank = queue.push(Job::new(
+            JobKind::Rank {
+                rank_type: RankType::DailyAI,
+                range: 1..50,
+                date: None,
+            },
+            options.clone(),
+        ));
+        queue.push(Job::new(
+            JobKind::Search {
+                word: "オリジナル".to_string(),
+                mode: SearchMode::Safe,
+                order: SearchOrder::Newest,
+                limit: 60,
+            },
+            JobOptions::default(),
+        ));
+        queue.fail(rank, "2 errors".to_string());
+
+        let content = toml::to_string(&queue).unwrap();
+        let mut loaded = toml::from_str::<JobQueue>(&content).unwrap();
+
+        assert!(content.contains("rank_type = \"daily_ai\""));
+        assert_eq!(loaded, queue);
+        assert_eq!(loaded.get(rank).unwrap().attempts, 1);
+        assert!(loaded.remove(artwork).is_some());
+        assert!(loaded.remove(artwork).is_none());
+        assert_eq!(loaded.push(Job::new(JobKind::User(2), options)), 4);
+    }
+
+    #[test]
+    fn test_queue_file() {
+        let path = temp_dir().join(format!("x-pixiv-queue-{}.toml", std::process::id()));
+        let file = QueueFile::new(&path);
+
+        let done = file
+            .update(|queue| queue.push(Job::new(JobKind::Artwork(1), JobOptions::default())))
+            .unwrap();
+        let cancelled = file
+            .update(|queue| queue.push(Job::new(JobKind::User(2), JobOptions::default())))
+            .unwrap();
+        file.finish(done, &Ok(JobSummary::default())).unwrap();
+        let interrupted = JobSummary {
+            cancelled: 3,
+            ..Default::default()
+        };
+        file.finish(cancelled, &Ok(interrupted)).unwrap();
+        let entries = file.update(|queue| queue.entries.clone()).unwrap();
+
+        fs::write(&path, "entries = [").unwrap();
+        let broken = file.update(|queue| queue.entries.clear());
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, cancelled);
+        assert_eq!(entries[0].attempts, 0);
+        assert!(broken.is_err());
+        assert_eq!(content, "entries = [");
+    }
+}
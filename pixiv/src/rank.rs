@@ -1,15 +1,21 @@
-use std::{fmt::Display, ops::Range};
+use std::{fmt::Display, ops::Range, str::FromStr};
 
+use serde::{Deserialize, Serialize};
+
+use crate::client;
 use crate::data::{Content, RankList};
 
 const RANK_URI: &str = "https://www.pixiv.net/ranking.php";
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
 pub enum RankType {
     Daily,
     Weekly,
     Monthly,
     Rookie,
     Original,
+    #[serde(rename = "daily_ai")]
     DailyAI,
     Male,
     Female,
@@ -36,10 +42,31 @@ impl Display for RankType {
     }
 }
 
+impl FromStr for RankType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use RankType::*;
+
+        match s {
+            "daily" => Ok(Daily),
+            "weekly" => Ok(Weekly),
+            "monthly" => Ok(Monthly),
+            "rookie" => Ok(Rookie),
+            "original" => Ok(Original),
+            "daily_ai" => Ok(DailyAI),
+            "male" => Ok(Male),
+            "female" => Ok(Female),
+            _ => Err(format!("unknown rank type: {}", s)),
+        }
+    }
+}
+
 pub struct Rank {
     rank_type: RankType,
     is_r18: bool,
     download_range: Range<usize>,
+    date: Option<String>,
     queue: Vec<Content>,
     current: usize,
 }
@@ -51,17 +78,30 @@ impl Rank {
             rank_type,
             is_r18,
             download_range,
+            date: None,
             queue: vec![],
             current: (start / 50) * 50,
         }
     }
 
+    /// The ranking of `date` (`YYYYMMDD`) instead of the latest one.
+    pub fn with_date(mut self, date: Option<String>) -> Self {
+        self.date = date;
+        self
+    }
+
     fn get_url(&self, page: usize) -> String {
         let is_r18 = if self.is_r18 { "_r18" } else { "" };
-        format!(
+        let mut url = format!(
             "{}?mode={}{}&format=json&p={}",
             RANK_URI, self.rank_type, is_r18, page
-        )
+        );
+
+        if let Some(date) = &self.date {
+            url.push_str(&format!("&date={}", date));
+        }
+
+        url
     }
 
     pub async fn next(&mut self) -> reqwest::Result<Option<Content>> {
@@ -69,7 +109,10 @@ impl Rank {
         if self.current - 1 > self.download_range.end {
             Ok(None)
         } else if self.queue.len() == 0 {
-            let response = reqwest::get(self.get_url((self.current / 50) + 1)).await?;
+            let response = client::api()
+                .get(self.get_url((self.current / 50) + 1))
+                .send()
+                .await?;
             if response.status() == 200 {
                 let data = response.json::<RankList>().await?;
                 let list = &mut if self.download_range.start > self.current {
@@ -0,0 +1,232 @@
+use std::{str::FromStr, time::Duration};
+
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, TimeZone, Timelike,
+};
+
+/// When a watched job runs.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schedule {
+    /// a fixed time after the last run
+    Every(Duration),
+    /// the next matching minute in local time
+    Cron(Cron),
+}
+
+/// A five field cron expression, `minute hour day-of-month month day-of-week`.
+///
+/// Fields accept `*`, numbers, ranges `a-b`, steps `*/n` or `a-b/n` and lists `a,b`.
+/// Like cron, a day matches either field when both day fields are restricted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    /// Parses an interval such as `90s`, `30m`, `6h`, `1d` or `1h30m`.
+    pub fn every(s: &str) -> Result<Self, String> {
+        let mut total = 0;
+        let mut number = String::new();
+
+        for c in s.trim().chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let unit = match c {
+                's' => 1,
+                'm' => 60,
+                'h' => 60 * 60,
+                'd' => 24 * 60 * 60,
+                _ => return Err(format!("unknown interval unit '{}' in {}", c, s)),
+            };
+            let value = number
+                .parse::<u64>()
+                .map_err(|_| format!("missing number before '{}' in {}", c, s))?;
+            total += value * unit;
+            number.clear();
+        }
+
+        if !number.is_empty() || total == 0 {
+            return Err(format!("invalid interval {}, e.g. 30m, 6h or 1d", s));
+        }
+
+        Ok(Schedule::Every(Duration::from_secs(total)))
+    }
+
+    pub fn cron(s: &str) -> Result<Self, String> {
+        Ok(Schedule::Cron(s.parse()?))
+    }
+
+    /// The first run time after `last`.
+    pub fn next_after(&self, last: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Schedule::Every(interval) => Some(last + ChronoDuration::from_std(*interval).ok()?),
+            Schedule::Cron(cron) => cron.next_after(last),
+        }
+    }
+}
+
+impl Cron {
+    /// The first matching minute after `time`, searched up to five years ahead.
+    pub fn next_after(&self, time: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start =
+            time.naive_local().with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let mut date = start.date();
+
+        for _ in 0..366 * 5 {
+            if self.matches_date(date) {
+                for hour in 0..24 {
+                    if !has(self.hours, hour) {
+                        continue;
+                    }
+                    for minute in 0..60 {
+                        let naive = date.and_hms_opt(hour, minute, 0)?;
+                        if !has(self.minutes, minute) || naive < start {
+                            continue;
+                        }
+                        // skipped by a daylight saving change
+                        if let Some(time) = Local.from_local_datetime(&naive).earliest() {
+                            return Some(time);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !has(self.months, date.month()) {
+            return false;
+        }
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("cron expression needs 5 fields: {}", s));
+        };
+        let mut weekday_bits = parse_field(weekdays, 0, 7)?;
+
+        // 7 is sunday too
+        if has(weekday_bits, 7) {
+            weekday_bits |= 1;
+        }
+
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekday_bits,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+/// Bit `n` is set when the field matches `n`.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("invalid step in {}", field))?,
+            ),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (parse_value(start, field)?, parse_value(end, field)?),
+                // `5/15` runs from 5 to the end
+                None if step > 1 => (parse_value(range, field)?, max),
+                None => {
+                    let value = parse_value(range, field)?;
+                    (value, value)
+                }
+            },
+        };
+
+        if start < min || end > max || start > end {
+            return Err(format!("{} is out of range {}-{}", field, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+fn parse_value(value: &str, field: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid cron field {}", field))
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use chrono::{Local, TimeZone};
+
+    use super::Schedule;
+
+    #[test]
+    fn test_schedule() {
+        assert_eq!(
+            Schedule::every("1h30m").unwrap(),
+            Schedule::Every(Duration::from_secs(5400))
+        );
+        assert!(Schedule::every("10").is_err());
+        assert!(Schedule::every("2w").is_err());
+        assert!(Schedule::cron("* * * *").is_err());
+        assert!(Schedule::cron("60 * * * *").is_err());
+
+        let time = |d, h, m| Local.with_ymd_and_hms(2024, 1, d, h, m, 0).unwrap();
+        let daily = Schedule::cron("0 3 * * *").unwrap();
+        assert_eq!(daily.next_after(time(1, 2, 59)), Some(time(1, 3, 0)));
+        assert_eq!(daily.next_after(time(1, 3, 0)), Some(time(2, 3, 0)));
+
+        let steps = Schedule::cron("*/15 9-17 * * 1-5").unwrap();
+        // 2024-01-06 is a saturday
+        assert_eq!(steps.next_after(time(5, 17, 45)), Some(time(8, 9, 0)));
+        assert_eq!(steps.next_after(time(8, 9, 1)), Some(time(8, 9, 15)));
+
+        // the 1st of the month or any sunday
+        let days = Schedule::cron("0 0 1 * 7").unwrap();
+        assert_eq!(days.next_after(time(2, 0, 0)), Some(time(7, 0, 0)));
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::client;
+use crate::data::{Api, Content, SearchList, SearchResult};
+
+const SEARCH_URI: &str = "https://www.pixiv.net/ajax/search/artworks";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    All,
+    Safe,
+    R18,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchOrder {
+    Newest,
+    Oldest,
+}
+
+impl Display for SearchMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use SearchMode::*;
+
+        write!(
+            f,
+            "{}",
+            match self {
+                All => "all",
+                Safe => "safe",
+                R18 => "r18",
+            }
+        )
+    }
+}
+
+impl Display for SearchOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use SearchOrder::*;
+
+        write!(
+            f,
+            "{}",
+            match self {
+                Newest => "date_d",
+                Oldest => "date",
+            }
+        )
+    }
+}
+
+/// Tag search results, fetched one page (60 artworks) at a time.
+pub struct Search {
+    word: String,
+    mode: SearchMode,
+    order: SearchOrder,
+    limit: usize,
+    queue: Vec<Content>,
+    page: usize,
+    count: usize,
+    done: bool,
+}
+
+impl Search {
+    pub fn new(word: &str, mode: SearchMode, order: SearchOrder, limit: usize) -> Self {
+        Self {
+            word: word.to_string(),
+            mode,
+            order,
+            limit,
+            queue: vec![],
+            page: 0,
+            count: 0,
+            done: false,
+        }
+    }
+
+    fn get_url(&self, page: usize) -> reqwest::Url {
+        let mut url = reqwest::Url::parse_with_params(
+            SEARCH_URI,
+            &[
+                ("word", self.word.as_str()),
+                ("order", &self.order.to_string()),
+                ("mode", &self.mode.to_string()),
+                ("p", &page.to_string()),
+                ("s_mode", "s_tag"),
+                ("type", "all"),
+            ],
+        )
+        .unwrap();
+        // tags such as "Fate/GrandOrder" are one segment
+        url.path_segments_mut().unwrap().push(&self.word);
+        url
+    }
+
+    pub async fn next(&mut self) -> reqwest::Result<Option<Content>> {
+        if self.count >= self.limit {
+            return Ok(None);
+        }
+
+        while self.queue.is_empty() && !self.done {
+            self.page += 1;
+            let data = client::api()
+                .get(self.get_url(self.page))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Api<SearchResult>>()
+                .await?;
+
+            self.add_page(data.body.illust_manga);
+        }
+
+        if self.queue.is_empty() {
+            Ok(None)
+        } else {
+            self.count += 1;
+            Ok(Some(self.queue.remove(0)))
+        }
+    }
+
+    fn add_page(&mut self, list: SearchList) {
+        self.done = list.data.is_empty();
+        self.queue = list
+            .data
+            .into_iter()
+            // ad containers
+            .filter(|item| item.id != 0)
+            .map(Content::from)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Search, SearchMode, SearchOrder};
+    use crate::data::{Api, SearchResult};
+
+    #[tokio::test]
+    async fn test() {
+        let mut search = Search::new("オリジナル", SearchMode::Safe, SearchOrder::Newest, 70);
+        let mut index = 0;
+        while search.next().await.unwrap().is_some() {
+            index += 1;
+        }
+        assert_eq!(index, 70);
+    }
+
+    #[test]
+    fn test_search_result() {
+        let json = r#"{"error":false,"body":{"illustManga":{"data":[
+            {"id":"106465672","title":"t","url":"https://i.pximg.net/c/250x250_80_a2/img-master/img/2023/03/23/00/05/02/106465672_p0_square1200.jpg",
+            "userName":"u","tags":["オリジナル"],"width":1200,"height":1600,"pageCount":1,
+            "xRestrict":1,"aiType":2},
+            {"isAdContainer":true}],
+            "total":1234},"popular":{"recent":[],"permanent":[]}}}"#;
+        let result = serde_json::from_str::<Api<SearchResult>>(json)
+            .unwrap()
+            .body;
+        let mut search = Search::new("オリジナル", SearchMode::All, SearchOrder::Newest, 10);
+
+        assert_eq!(result.illust_manga.total, 1234);
+        search.add_page(result.illust_manga);
+
+        assert!(!search.done);
+        assert_eq!(search.queue.len(), 1);
+        assert_eq!(search.queue[0].illust_id, 106465672);
+        assert_eq!(search.queue[0].x_restrict, Some(1));
+        assert_eq!(search.queue[0].ai_type, Some(2));
+        assert_eq!(
+            search.get_url(2).as_str(),
+            "https://www.pixiv.net/ajax/search/artworks/%E3%82%AA%E3%83%AA%E3%82%B8%E3%83%8A%E3%83%AB?word=%E3%82%AA%E3%83%AA%E3%82%B8%E3%83%8A%E3%83%AB&order=date_d&mode=all&p=2&s_mode=s_tag&type=all"
+        );
+        assert_eq!(
+            Search::new("Fate/GO #1?", SearchMode::All, SearchOrder::Newest, 10)
+                .get_url(1)
+                .as_str(),
+            "https://www.pixiv.net/ajax/search/artworks/Fate%2FGO%20%231%3F?word=Fate%2FGO+%231%3F&order=date_d&mode=all&p=1&s_mode=s_tag&type=all"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use crate::client;
+use crate::data::{Api, SeriesBody, SeriesWork};
+
+/// A manga series, episodes are listed first to last.
+pub struct Series {
+    id: usize,
+}
+
+impl Series {
+    pub fn new(id: usize) -> Self {
+        Self { id }
+    }
+
+    fn get_url(&self, page: usize) -> String {
+        format!("https://www.pixiv.net/ajax/series/{}?p={}", self.id, page)
+    }
+
+    pub async fn get_artworks(&self) -> reqwest::Result<Vec<usize>> {
+        let mut works = vec![];
+
+        for page in 1.. {
+            let data = client::api()
+                .get(self.get_url(page))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Api<SeriesBody>>()
+                .await?;
+            let list = data.body.page;
+            let done = list.series.is_empty() || works.len() + list.series.len() >= list.total;
+
+            works.extend(list.series);
+            if done {
+                break;
+            }
+        }
+
+        Ok(episodes(works))
+    }
+}
+
+/// Artwork ids in episode order, the pages don't list them in order.
+fn episodes(mut works: Vec<SeriesWork>) -> Vec<usize> {
+    works.sort_by_key(|work| work.order);
+    works.dedup_by_key(|work| work.work_id);
+    works.into_iter().map(|work| work.work_id).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::episodes;
+    use crate::data::{Api, SeriesBody};
+
+    #[test]
+    fn test_series_page() {
+        let json = r#"{"error":false,"body":{"page":{"series":[
+            {"workId":"103","order":3},{"workId":"101","order":1},{"workId":102,"order":2}
+            ],"total":3}}}"#;
+        let data = serde_json::from_str::<Api<SeriesBody>>(json).unwrap();
+
+        assert_eq!(data.body.page.total, 3);
+        assert_eq!(episodes(data.body.page.series), vec![101, 102, 103]);
+    }
+}
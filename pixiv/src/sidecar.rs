@@ -0,0 +1,279 @@
+use std::{
+    fmt::Display,
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{data::ArtworksData, job::clean_name};
+
+/// Bumped whenever a field is renamed or removed.
+pub const SIDECAR_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum SidecarFormat {
+    Json,
+    Toml,
+}
+
+/// Metadata written next to the downloaded images, see README for the schema.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Sidecar {
+    pub version: u32,
+    pub illust_id: usize,
+    pub title: String,
+    pub description: String,
+    pub user_id: usize,
+    pub user_name: String,
+    pub tags: Vec<SidecarTag>,
+    pub create_date: String,
+    pub upload_date: String,
+    pub page_count: usize,
+    pub width: u32,
+    pub height: u32,
+    pub x_restrict: u8,
+    pub ai_type: u8,
+    pub bookmark_count: usize,
+    pub like_count: usize,
+    pub view_count: usize,
+    /// position in the ranking the artwork was downloaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<usize>,
+    pub source_url: String,
+    /// set for an episode of a manga series
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series: Option<SidecarSeries>,
+    pub download: Provenance,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SidecarSeries {
+    pub series_id: usize,
+    pub title: String,
+    /// episode number, starting at 1
+    pub order: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SidecarTag {
+    pub name: String,
+    pub translation: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Provenance {
+    /// unix timestamp in seconds
+    pub downloaded_at: u64,
+    pub tool: String,
+    pub files: Vec<SidecarFile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SidecarFile {
+    pub url: String,
+    pub path: String,
+}
+
+impl Sidecar {
+    pub fn new(data: &ArtworksData, files: Vec<(String, PathBuf)>) -> Self {
+        Self {
+            version: SIDECAR_VERSION,
+            illust_id: data.id,
+            title: data.title.clone(),
+            description: data.description.clone(),
+            user_id: data.user_id,
+            user_name: data.user_name.clone(),
+            tags: data
+                .tags
+                .tags
+                .iter()
+                .map(|tag| SidecarTag {
+                    name: tag.tag.clone(),
+                    translation: tag
+                        .translation
+                        .as_ref()
+                        .and_then(|translation| translation.get("en").cloned()),
+                })
+                .collect(),
+            create_date: data.create_date.clone(),
+            upload_date: data.upload_date.clone(),
+            page_count: data.page_count,
+            width: data.width,
+            height: data.height,
+            x_restrict: data.x_restrict,
+            ai_type: data.ai_type,
+            bookmark_count: data.bookmark_count,
+            like_count: data.like_count,
+            view_count: data.view_count,
+            rank: None,
+            source_url: format!("https://www.pixiv.net/artworks/{}", data.id),
+            series: data.series.as_ref().map(|series| SidecarSeries {
+                series_id: series.series_id,
+                title: series.title.clone(),
+                order: series.order,
+            }),
+            download: Provenance {
+                downloaded_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|time| time.as_secs())
+                    .unwrap_or(0),
+                tool: format!("x-pixiv-lib {}", env!("CARGO_PKG_VERSION")),
+                files: files
+                    .into_iter()
+                    .map(|(url, path)| SidecarFile {
+                        url,
+                        path: path.to_string_lossy().to_string(),
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    /// Expands `{illust_id}`, `{title}`, `{user_id}` and `{user_name}` in `template`, like
+    /// the file names of the pages.
+    pub fn file_name(&self, template: &str, format: SidecarFormat) -> String {
+        let name = template
+            .replace("{illust_id}", &self.illust_id.to_string())
+            .replace("{title}", &clean_name(&self.title))
+            .replace("{user_id}", &self.user_id.to_string())
+            .replace("{user_name}", &clean_name(&self.user_name));
+
+        format!("{}.{}", name, format)
+    }
+
+    pub fn to_string(&self, format: SidecarFormat) -> String {
+        match format {
+            SidecarFormat::Json => serde_json::to_string_pretty(self).unwrap(),
+            SidecarFormat::Toml => toml::to_string(self).unwrap(),
+        }
+    }
+
+    pub fn write(&self, dir: &Path, template: &str, format: SidecarFormat) -> io::Result<PathBuf> {
+        let path = dir.join(self.file_name(template, format));
+
+        create_dir_all(dir)?;
+        write(&path, self.to_string(format))?;
+
+        Ok(path)
+    }
+
+    /// The downloaded pages that still exist, looked up next to the sidecar at `path`
+    /// first since the folder may have moved since the download.
+    pub fn pages(&self, path: &Path) -> Vec<PathBuf> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        self.download
+            .files
+            .iter()
+            .filter_map(|file| {
+                let page = Path::new(&file.path);
+                page.file_name()
+                    .map(|name| dir.join(name))
+                    .filter(|moved| moved.exists())
+                    .or_else(|| page.exists().then(|| page.to_path_buf()))
+            })
+            .collect()
+    }
+}
+
+/// The JSON and TOML sidecars in `dir` and its sub folders, other files with these
+/// extensions are left out. Hidden folders such as the gallery thumbnails are not searched.
+pub fn find_sidecars(dir: &Path) -> io::Result<Vec<(PathBuf, Sidecar)>> {
+    let mut sidecars = vec![];
+
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        if path.is_dir() {
+            if !name.starts_with('.') {
+                sidecars.append(&mut find_sidecars(&path)?);
+            }
+            continue;
+        }
+        let sidecar = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str::<Sidecar>(&read_to_string(&path)?).ok(),
+            Some("toml") => toml::from_str::<Sidecar>(&read_to_string(&path)?).ok(),
+            _ => None,
+        };
+        if let Some(sidecar) = sidecar {
+            sidecars.push((path, sidecar));
+        }
+    }
+
+    Ok(sidecars)
+}
+
+impl Display for SidecarFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use SidecarFormat::*;
+
+        write!(
+            f,
+            "{}",
+            match self {
+                Json => "json",
+                Toml => "toml",
+            }
+        )
+    }
+}
+
+impl FromStr for SidecarFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(SidecarFormat::Json),
+            "toml" => Ok(SidecarFormat::Toml),
+            _ => Err(format!("unknown sidecar format: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Sidecar, SidecarFormat};
+    use crate::data::ArtworksData;
+
+    #[test]
+    fn test_sidecar() {
+        let json = r#"{"illustId":"123","title":"t","description":"d","userId":"45","userName":"u",
+            "tags":{"tags":[{"tag":"a","translation":{"en":"b"}},{"tag":"c"}]}}"#;
+        let data = serde_json::from_str::<ArtworksData>(json).unwrap();
+        let sidecar = Sidecar::new(&data, vec![]);
+
+        assert_eq!(sidecar.illust_id, 123);
+        assert_eq!(sidecar.user_id, 45);
+        assert_eq!(sidecar.tags[0].translation.as_deref(), Some("b"));
+        assert_eq!(sidecar.tags[1].translation, None);
+        assert_eq!(
+            sidecar.file_name("{user_name}-{illust_id}", SidecarFormat::Toml),
+            "u-123.toml"
+        );
+        let sidecar = Sidecar {
+            title: "a/b\\c".to_string(),
+            ..sidecar
+        };
+        assert_eq!(
+            sidecar.file_name("{title}-{illust_id}", SidecarFormat::Json),
+            "a_b_c-123.json"
+        );
+        assert!(sidecar
+            .to_string(SidecarFormat::Toml)
+            .contains("illust_id = 123"));
+
+        let json = json.replace(
+            "}]}}",
+            r#"}]},"seriesNavData":{"seriesId":"9","title":"s","order":2}}"#,
+        );
+        let data = serde_json::from_str::<ArtworksData>(&json).unwrap();
+        let toml = Sidecar::new(&data, vec![]).to_string(SidecarFormat::Toml);
+        let sidecar = toml::from_str::<Sidecar>(&toml).unwrap();
+        assert_eq!(sidecar.series.unwrap().order, 2);
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Failed runs after which an artwork is given up.
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// Incremental sync progress, keyed by user id.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct SyncState {
+    #[serde(default)]
+    pub users: BTreeMap<String, UserSync>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct UserSync {
+    /// highest illust id already downloaded, failed or given up
+    pub last_id: usize,
+    /// failed attempts of the artworks that are retried, keyed by illust id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub failed: BTreeMap<String, u32>,
+}
+
+impl SyncState {
+    pub fn last_id(&self, user_id: usize) -> Option<usize> {
+        self.users
+            .get(&user_id.to_string())
+            .map(|user| user.last_id)
+    }
+
+    /// Returns the ids newer than the last synced one and the failed ones to retry,
+    /// oldest first. Failed artworks the user no longer lists are forgotten.
+    pub fn new_artworks(&mut self, user_id: usize, ids: &[usize]) -> Vec<usize> {
+        let last_id = self.last_id(user_id).unwrap_or(0);
+        let mut retried = vec![];
+
+        if let Some(user) = self.users.get_mut(&user_id.to_string()) {
+            user.failed
+                .retain(|id, _| id.parse().is_ok_and(|id: usize| ids.contains(&id)));
+            retried.extend(user.failed.keys().filter_map(|id| id.parse::<usize>().ok()));
+        }
+
+        let mut new_ids = ids
+            .iter()
+            .copied()
+            .filter(|id| *id > last_id || retried.contains(id))
+            .collect::<Vec<usize>>();
+
+        new_ids.sort_unstable();
+        new_ids
+    }
+
+    /// Records a downloaded artwork.
+    pub fn update(&mut self, user_id: usize, illust_id: usize) {
+        let user = self.user(user_id, illust_id);
+        user.failed.remove(&illust_id.to_string());
+    }
+
+    /// Records an artwork that failed, it is retried on the next runs until it failed
+    /// [`MAX_ATTEMPTS`] times. Returns whether it is retried.
+    pub fn fail(&mut self, user_id: usize, illust_id: usize) -> bool {
+        let user = self.user(user_id, illust_id);
+        let attempts = user.failed.entry(illust_id.to_string()).or_default();
+
+        *attempts += 1;
+        if *attempts < MAX_ATTEMPTS {
+            return true;
+        }
+        user.failed.remove(&illust_id.to_string());
+        false
+    }
+
+    /// Records an artwork that was cancelled, it is retried on the next run without
+    /// counting as an attempt.
+    pub fn cancel(&mut self, user_id: usize, illust_id: usize) {
+        let user = self.user(user_id, illust_id);
+        user.failed.entry(illust_id.to_string()).or_default();
+    }
+
+    /// The sync of a user, with `last_id` moved past `illust_id`.
+    fn user(&mut self, user_id: usize, illust_id: usize) -> &mut UserSync {
+        let user = self.users.entry(user_id.to_string()).or_default();
+        user.last_id = user.last_id.max(illust_id);
+        user
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SyncState, MAX_ATTEMPTS};
+
+    #[test]
+    fn test_new_artworks() {
+        let mut state = SyncState::default();
+
+        assert_eq!(state.new_artworks(1, &[30, 10, 20]), vec![10, 20, 30]);
+        state.update(1, 20);
+        state.update(1, 10);
+        assert_eq!(state.last_id(1), Some(20));
+        assert_eq!(state.new_artworks(1, &[30, 10, 20, 40]), vec![30, 40]);
+        assert_eq!(state.new_artworks(2, &[5]), vec![5]);
+    }
+
+    #[test]
+    fn test_failed_artworks() {
+        let mut state = SyncState::default();
+
+        // a failed artwork doesn't hold back the newer ones
+        assert!(state.fail(1, 10));
+        state.update(1, 20);
+        state.cancel(1, 30);
+        assert_eq!(state.last_id(1), Some(30));
+        assert_eq!(state.new_artworks(1, &[10, 20, 30, 40]), vec![10, 30, 40]);
+
+        for _ in 2..MAX_ATTEMPTS {
+            assert!(state.fail(1, 10));
+        }
+        assert!(!state.fail(1, 10));
+        state.update(1, 30);
+        assert_eq!(state.new_artworks(1, &[10, 20, 30, 40]), vec![40]);
+
+        // deleted artworks are forgotten
+        state.fail(1, 40);
+        assert_eq!(state.new_artworks(1, &[10, 20, 30]), Vec::<usize>::new());
+        assert!(state.users["1"].failed.is_empty());
+
+        let toml = toml::to_string(&state).unwrap();
+        assert!(toml::from_str::<SyncState>(&toml).is_ok());
+    }
+}
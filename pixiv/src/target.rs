@@ -0,0 +1,240 @@
+use std::{fmt::Display, str::FromStr};
+
+use reqwest::Url;
+
+use crate::rank::RankType;
+
+/// What a pixiv url points to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    Artwork(usize),
+    User(usize),
+    /// a manga series
+    Series {
+        user_id: usize,
+        series_id: usize,
+    },
+    Novel(usize),
+    NovelSeries(usize),
+    Ranking {
+        rank_type: RankType,
+        r18: bool,
+        /// `YYYYMMDD`, the latest ranking when not set
+        date: Option<String>,
+    },
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    /// Parses page urls of `pixiv.net` and image urls of `pximg.net`, the scheme is optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let url = if s.contains("://") {
+            Url::parse(s)
+        } else {
+            Url::parse(&format!("https://{}", s))
+        }
+        .map_err(|_| format!("not a url: {}", s))?;
+        let host = url.host_str().unwrap_or_default();
+        let mut segments = url
+            .path_segments()
+            .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+            .unwrap_or_else(Vec::new);
+        let query = |key: &str| {
+            url.query_pairs()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.into_owned())
+        };
+        let unknown = || {
+            format!(
+                "not a pixiv artwork, user, series, novel or ranking url: {}",
+                s
+            )
+        };
+
+        if host == "pximg.net" || host.ends_with(".pximg.net") {
+            // .../img/2023/03/23/00/05/02/106465672_p0_master1200.jpg
+            return segments
+                .last()
+                .and_then(|name| name.split(['_', '.']).next())
+                .and_then(|id| id.parse().ok())
+                .map(Target::Artwork)
+                .ok_or_else(unknown);
+        }
+        if host != "pixiv.net" && !host.ends_with(".pixiv.net") {
+            return Err(unknown());
+        }
+        // language prefix, e.g. /en/artworks/123
+        if segments
+            .first()
+            .is_some_and(|first| first.len() == 2 || first.starts_with("zh-"))
+        {
+            segments.remove(0);
+        }
+
+        let target = match segments[..] {
+            ["artworks", id, ..] | ["i", id] => id.parse().ok().map(Target::Artwork),
+            ["users", id, ..] | ["u", id] => id.parse().ok().map(Target::User),
+            ["user", user_id, "series", series_id, ..] => user_id
+                .parse()
+                .ok()
+                .zip(series_id.parse().ok())
+                .map(|(user_id, series_id)| Target::Series { user_id, series_id }),
+            ["novel", "show.php"] => query("id")
+                .and_then(|id| id.parse().ok())
+                .map(Target::Novel),
+            ["novel", "series", id, ..] => id.parse().ok().map(Target::NovelSeries),
+            ["member_illust.php"] => match query("illust_id") {
+                Some(id) => id.parse().ok().map(Target::Artwork),
+                None => query("id").and_then(|id| id.parse().ok()).map(Target::User),
+            },
+            ["member.php"] => query("id").and_then(|id| id.parse().ok()).map(Target::User),
+            ["ranking.php"] => {
+                let mode = query("mode").unwrap_or_else(|| "daily".to_string());
+                let r18 = mode.contains("_r18");
+                let rank_type = mode.replacen("_r18", "", 1).parse::<RankType>()?;
+
+                Some(Target::Ranking {
+                    rank_type,
+                    r18,
+                    date: query("date"),
+                })
+            }
+            _ => None,
+        };
+        target.ok_or_else(unknown)
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Artwork(id) => write!(f, "artwork {}", id),
+            Target::User(id) => write!(f, "user {}", id),
+            Target::Series { user_id, series_id } => {
+                write!(f, "series {} of user {}", series_id, user_id)
+            }
+            Target::Novel(id) => write!(f, "novel {}", id),
+            Target::NovelSeries(id) => write!(f, "novel series {}", id),
+            Target::Ranking {
+                rank_type,
+                r18,
+                date,
+            } => {
+                write!(f, "ranking {}", rank_type)?;
+                if *r18 {
+                    write!(f, " r18")?;
+                }
+                match date {
+                    Some(date) => write!(f, " {}", date),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// An artwork id, or a url of the artwork or one of its images.
+pub fn artwork_id(s: &str) -> Result<usize, String> {
+    match s.trim().parse() {
+        Ok(id) => Ok(id),
+        Err(_) => match s.parse()? {
+            Target::Artwork(id) => Ok(id),
+            target => Err(format!("{} is not an artwork", target)),
+        },
+    }
+}
+
+/// A user id, or a url of the user's profile.
+pub fn user_id(s: &str) -> Result<usize, String> {
+    match s.trim().parse() {
+        Ok(id) => Ok(id),
+        Err(_) => match s.parse()? {
+            Target::User(id) => Ok(id),
+            target => Err(format!("{} is not a user", target)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{artwork_id, user_id, Target};
+    use crate::rank::RankType;
+
+    #[test]
+    fn test_target() {
+        let parse = |s: &str| s.parse::<Target>().unwrap();
+
+        assert_eq!(
+            parse("https://www.pixiv.net/artworks/123"),
+            Target::Artwork(123)
+        );
+        assert_eq!(
+            parse("www.pixiv.net/en/artworks/123#manga"),
+            Target::Artwork(123)
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/member_illust.php?mode=medium&illust_id=123"),
+            Target::Artwork(123)
+        );
+        assert_eq!(
+            parse("https://i.pximg.net/img-original/img/2023/03/23/00/05/02/106465672_p0.png"),
+            Target::Artwork(106465672)
+        );
+        assert_eq!(
+            parse("https://i.pximg.net/c/250x250_80_a2/img-master/img/2023/03/23/00/05/02/106465672_p0_square1200.jpg"),
+            Target::Artwork(106465672)
+        );
+        assert_eq!(parse("https://www.pixiv.net/users/456"), Target::User(456));
+        assert_eq!(
+            parse("https://www.pixiv.net/en/users/456/illustrations"),
+            Target::User(456)
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/member.php?id=456"),
+            Target::User(456)
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/user/456/series/789"),
+            Target::Series {
+                user_id: 456,
+                series_id: 789
+            }
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/novel/show.php?id=10"),
+            Target::Novel(10)
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/novel/series/11"),
+            Target::NovelSeries(11)
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/ranking.php?mode=weekly_r18&content=illust&date=20240101"),
+            Target::Ranking {
+                rank_type: RankType::Weekly,
+                r18: true,
+                date: Some("20240101".to_string())
+            }
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/ranking.php"),
+            Target::Ranking {
+                rank_type: RankType::Daily,
+                r18: false,
+                date: None
+            }
+        );
+        assert!("https://example.com/artworks/1".parse::<Target>().is_err());
+        assert!("https://www.pixiv.net/tags/cat".parse::<Target>().is_err());
+        assert!("https://www.pixiv.net/ranking.php?mode=bogus"
+            .parse::<Target>()
+            .is_err());
+
+        assert_eq!(artwork_id("123"), Ok(123));
+        assert_eq!(artwork_id("https://www.pixiv.net/artworks/1"), Ok(1));
+        assert!(artwork_id("https://www.pixiv.net/users/1").is_err());
+        assert_eq!(user_id("https://www.pixiv.net/users/1"), Ok(1));
+    }
+}
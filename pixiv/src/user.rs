@@ -1,3 +1,4 @@
+use crate::client;
 use crate::data::{Api, Illusts};
 
 use std::collections::HashMap;
@@ -12,14 +13,16 @@ impl User {
     }
 
     pub async fn get_artworks(&self) -> reqwest::Result<Vec<usize>> {
-        let data = reqwest::get(format!(
-            "https://www.pixiv.net/ajax/user/{}/profile/all",
-            self.id
-        ))
-        .await?
-        .error_for_status()?
-        .json::<Api<Illusts<HashMap<usize, Option<bool>>>>>()
-        .await?;
+        let data = client::api()
+            .get(format!(
+                "https://www.pixiv.net/ajax/user/{}/profile/all",
+                self.id
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Api<Illusts<HashMap<usize, Option<bool>>>>>()
+            .await?;
         let images = data
             .body
             .illusts
@@ -0,0 +1,67 @@
+use std::{
+    fs::read_to_string,
+    io::{self, read_to_string as read_all, stdin, ErrorKind},
+};
+
+use super::output;
+
+/// Parsed entries of the arguments and of the `--input` file, `-` reads stdin.
+///
+/// Every line of a file is one entry, empty lines and everything after a `#` that
+/// starts the line or follows a space are ignored. Entries that can't be parsed are
+/// reported and left out, duplicates are dropped.
+pub fn read_batch<T: PartialEq>(
+    args: &[String],
+    input: Option<&str>,
+    parse: fn(&str) -> Result<T, String>,
+) -> io::Result<Vec<T>> {
+    let mut entries = vec![];
+
+    for arg in args {
+        match arg.as_str() {
+            "-" => entries.extend(lines("stdin", &read_all(stdin())?)),
+            _ => entries.push((arg.clone(), arg.clone())),
+        }
+    }
+    match input {
+        Some("-") => entries.extend(lines("stdin", &read_all(stdin())?)),
+        Some(path) => {
+            let content = read_to_string(path)
+                .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path, err)))?;
+            entries.extend(lines(path, &content));
+        }
+        None => {}
+    }
+
+    let mut parsed = vec![];
+    for (source, entry) in entries {
+        match parse(&entry) {
+            Ok(value) if !parsed.contains(&value) => parsed.push(value),
+            Ok(_) => {}
+            Err(err) => {
+                let err = io::Error::new(ErrorKind::InvalidInput, err);
+                output::error(None, None, &err, format!("{}: {}", source, err));
+            }
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// `(source, entry)` of every line that is not empty or a comment.
+fn lines(name: &str, content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let line = match line.find(" #") {
+                Some(comment) => &line[..comment],
+                None => line,
+            }
+            .trim();
+
+            (!line.is_empty() && !line.starts_with('#'))
+                .then(|| (format!("{} line {}", name, index + 1), line.to_string()))
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use clap::Args;
+use x_pixiv_lib::{
+    archive::{Archive, ArchiveEntry},
+    dupes::{self, Dhash, DEFAULT_DISTANCE},
+};
+
+use super::{
+    output::{self, DupeFile, Event},
+    pipeline,
+};
+
+#[derive(Args)]
+pub struct DupesArgs {
+    /// Hamming distance up to which two images are duplicates, 0 only matches equal hashes
+    #[arg(default_value_t = DEFAULT_DISTANCE, short = 'd', long)]
+    distance: u32,
+}
+
+/// Lists groups of archived images that look the same.
+///
+/// Without `--archive` the archive is `./archive.jsonl`. Archived images without a hash
+/// are hashed first and the hashes recorded, so the first run over an old archive is slow.
+pub async fn dupes(args: &DupesArgs) -> x_pixiv_lib::Result<()> {
+    if let Err(err) = run(args) {
+        output::error(None, None, &err, format!("dupes: {}", err));
+    }
+
+    Ok(())
+}
+
+fn run(args: &DupesArgs) -> io::Result<()> {
+    let archive = match pipeline::archive() {
+        Some(archive) => archive,
+        None => Arc::new(Mutex::new(Archive::open("./archive.jsonl")?)),
+    };
+    let mut archive = archive.lock().unwrap();
+
+    hash_missing(&mut archive)?;
+
+    let clusters = dupes::clusters(&archive, args.distance);
+    for (index, cluster) in clusters.iter().enumerate() {
+        let files = cluster
+            .iter()
+            .map(|(entry, file)| DupeFile {
+                illust_id: entry.illust_id,
+                page: file.page,
+                path: file.path.to_string_lossy().to_string(),
+                title: &entry.title,
+                user_name: &entry.user_name,
+                distance: file
+                    .dhash
+                    .zip(cluster[0].1.dhash)
+                    .map(|(hash, first)| hash.distance(&first))
+                    .unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+
+        if !output::is_json() {
+            println!("#{} ({} images)", index + 1, files.len());
+            for file in &files {
+                println!(
+                    "  {} p{} {} ({} / {}), distance {}",
+                    file.illust_id, file.page, file.path, file.title, file.user_name, file.distance
+                );
+            }
+        }
+        output::emit(Event::DupeCluster { files });
+    }
+
+    if !output::is_json() {
+        println!("{} groups of duplicates", clusters.len());
+    }
+
+    Ok(())
+}
+
+/// Hashes the archived images that still exist and have no hash, converted images
+/// through their original when it was kept.
+fn hash_missing(archive: &mut Archive) -> io::Result<()> {
+    let updated = archive
+        .entries()
+        .filter_map(|entry| {
+            let mut entry = entry.clone();
+            let mut changed = false;
+
+            for file in entry.files.iter_mut().filter(|file| file.dhash.is_none()) {
+                // AVIF and JPEG XL can't be decoded, a kept original can
+                let path = match &file.conversion {
+                    Some(conversion) if conversion.original_kept => &conversion.original,
+                    _ => &file.path,
+                };
+                // removed, moved or not an image, e.g. the zip of an ugoira
+                if let Ok(hash) = Dhash::open(path) {
+                    file.dhash = Some(hash);
+                    changed = true;
+                }
+            }
+
+            changed.then_some(entry)
+        })
+        .collect::<Vec<ArchiveEntry>>();
+
+    for entry in updated {
+        archive.record(entry)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::{
+    fs::File,
+    io::{self, stdout, Write},
+    path::Path,
+};
+
+use clap::{Args, ValueEnum};
+use x_pixiv_lib::{host::image_hosts, job::ArtworkFiles};
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// resolve the original image urls and write them for an external downloader instead of downloading
+    #[arg(value_enum, long)]
+    pub export_urls: Option<ExportFormat>,
+
+    /// export output file, defaults to stdout
+    #[arg(long)]
+    pub export_file: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ExportFormat {
+    /// aria2c input file (aria2c -i FILE)
+    Aria2,
+    /// one url per line
+    Urls,
+    /// curl config file (curl -K FILE)
+    Curl,
+}
+
+impl ExportArgs {
+    pub fn write(&self, artworks: &[ArtworkFiles], format: ExportFormat) -> io::Result<()> {
+        let content = export(artworks, format);
+
+        match &self.export_file {
+            Some(path) => File::create(path)?.write_all(content.as_bytes()),
+            None => stdout().write_all(content.as_bytes()),
+        }
+    }
+}
+
+pub fn export(artworks: &[ArtworkFiles], format: ExportFormat) -> String {
+    // the preferred image host, the others are only used for failover
+    let image_host = image_hosts().remove(0);
+    let mut output = String::new();
+
+    if let ExportFormat::Curl = format {
+        output.push_str("create-dirs\n");
+    }
+
+    for (url, path) in artworks.iter().flat_map(|artwork| &artwork.files) {
+        let url = image_host.rewrite(url);
+
+        match format {
+            ExportFormat::Urls => output.push_str(&format!("{}\n", url)),
+            ExportFormat::Aria2 => {
+                output.push_str(&format!("{}\n", url));
+                if image_host.referer {
+                    output.push_str("  referer=https://www.pixiv.net/\n");
+                }
+                if let Some(dir) = path.parent().filter(|dir| dir != &Path::new("")) {
+                    output.push_str(&format!("  dir={}\n", dir.to_string_lossy()));
+                }
+                if let Some(name) = path.file_name() {
+                    output.push_str(&format!("  out={}\n", name.to_string_lossy()));
+                }
+            }
+            ExportFormat::Curl => {
+                output.push_str(&format!("url = \"{}\"\n", escape(&url)));
+                output.push_str(&format!(
+                    "output = \"{}\"\n",
+                    escape(&path.to_string_lossy())
+                ));
+                if image_host.referer {
+                    output.push_str("header = \"Referer: https://www.pixiv.net/\"\n");
+                }
+            }
+        }
+    }
+
+    output
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
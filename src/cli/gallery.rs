@@ -0,0 +1,65 @@
+use std::{io, path::Path, thread::available_parallelism};
+
+use clap::Args;
+use x_pixiv_lib::{archive::Archive, gallery::Gallery};
+
+use super::{
+    output::{self, Event},
+    pipeline,
+};
+
+#[derive(Args)]
+pub struct GalleryArgs {
+    /// downloaded folder, the index is written to PATH/index.html
+    path: String,
+
+    /// threads making thumbnails, defaults to the number of cpus
+    #[arg(short = 't', long)]
+    threads: Option<usize>,
+}
+
+/// Writes an offline HTML index of the artworks in a downloaded folder.
+///
+/// Artworks come from the sidecars in the folder, then from the archive (`--archive`,
+/// default `./archive.jsonl`) for files without a sidecar.
+pub async fn gallery(args: &GalleryArgs) -> x_pixiv_lib::Result<()> {
+    if let Err(err) = run(args) {
+        output::error(None, None, &err, format!("{}: {}", args.path, err));
+    }
+
+    Ok(())
+}
+
+fn run(args: &GalleryArgs) -> io::Result<()> {
+    let mut gallery = Gallery::new(&args.path)?;
+
+    gallery.add_sidecars()?;
+    match pipeline::archive() {
+        Some(archive) => gallery.add_archive(&archive.lock().unwrap()),
+        None => gallery.add_archive(&Archive::open("./archive.jsonl")?),
+    };
+
+    let threads = args
+        .threads
+        .unwrap_or_else(|| available_parallelism().map_or(1, |threads| threads.get()));
+    let summary = gallery.write(threads)?;
+    let index = Path::new(&args.path).join("index.html");
+
+    if !output::is_json() {
+        println!(
+            "{}: {} artworks, {} new thumbnails, {} without preview",
+            index.display(),
+            summary.artworks,
+            summary.thumbnails,
+            summary.failed
+        );
+    }
+    output::emit(Event::GalleryWritten {
+        path: &index.to_string_lossy(),
+        artworks: summary.artworks,
+        thumbnails: summary.thumbnails,
+        failed: summary.failed,
+    });
+
+    Ok(())
+}
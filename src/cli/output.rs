@@ -0,0 +1,288 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use clap::ValueEnum;
+use indicatif::{MultiProgress, ProgressDrawTarget};
+use serde::Serialize;
+
+/// All downloads finished.
+pub const EXIT_OK: i32 = 0;
+/// The command was aborted, e.g. the ranking or user could not be fetched.
+pub const EXIT_FATAL: i32 = 1;
+/// The command finished but some artworks or files failed.
+pub const EXIT_PARTIAL: i32 = 3;
+/// The downloads were cancelled with Ctrl-C.
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+static MODE: OnceLock<OutputMode> = OnceLock::new();
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static SUMMARY: Mutex<Summary> = Mutex::new(Summary {
+    artworks: 0,
+    files: 0,
+    bytes: 0,
+    skipped: 0,
+    errors: 0,
+    duplicates: 0,
+});
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum OutputMode {
+    /// progress bars and messages
+    Human,
+    /// newline-delimited JSON events on stdout
+    Json,
+}
+
+/// Events written in `--output json` mode, one JSON object per line.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    ArtworkResolved {
+        illust_id: usize,
+        title: &'a str,
+        user_name: &'a str,
+        pages: usize,
+    },
+    FileStarted {
+        illust_id: usize,
+        page: usize,
+        url: &'a str,
+        path: &'a str,
+    },
+    Progress {
+        illust_id: usize,
+        page: usize,
+        bytes: u64,
+        total: u64,
+    },
+    FileFinished {
+        illust_id: usize,
+        page: usize,
+        path: &'a str,
+        bytes: u64,
+        sha256: &'a str,
+    },
+    Skipped {
+        illust_id: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        page: Option<usize>,
+        reason: &'a str,
+    },
+    Error {
+        illust_id: Option<usize>,
+        page: Option<usize>,
+        kind: &'a str,
+        message: String,
+    },
+    /// a downloaded image was converted
+    Converted {
+        illust_id: usize,
+        page: usize,
+        path: &'a str,
+        bytes: u64,
+        original: &'a str,
+        original_bytes: u64,
+        original_kept: bool,
+    },
+    /// a downloaded image looks like an image of another archived artwork
+    Duplicate {
+        illust_id: usize,
+        page: usize,
+        path: &'a str,
+        duplicate_of: usize,
+        duplicate_page: usize,
+        distance: u32,
+        removed: bool,
+    },
+    /// one group of the `dupes` command
+    DupeCluster {
+        files: Vec<DupeFile<'a>>,
+    },
+    /// `index.html` of the `gallery` command
+    GalleryWritten {
+        path: &'a str,
+        artworks: usize,
+        thumbnails: usize,
+        failed: usize,
+    },
+    /// a PDF of the `pdf` command
+    PdfWritten {
+        path: &'a str,
+        artworks: usize,
+        pages: usize,
+    },
+    /// one run of a `watch` entry
+    WatchRun {
+        name: &'a str,
+        artworks: usize,
+        files: usize,
+        skipped: usize,
+        errors: usize,
+    },
+    /// one entry of the subscriptions file was synced
+    SubscriptionSynced {
+        name: &'a str,
+        artworks: usize,
+        files: usize,
+        skipped: usize,
+        errors: usize,
+    },
+    Summary(Summary),
+}
+
+#[derive(Serialize, Clone, Copy)]
+pub struct Summary {
+    artworks: usize,
+    files: usize,
+    bytes: u64,
+    skipped: usize,
+    errors: usize,
+    duplicates: usize,
+}
+
+#[derive(Serialize)]
+pub struct DupeFile<'a> {
+    pub illust_id: usize,
+    pub page: usize,
+    pub path: String,
+    pub title: &'a str,
+    pub user_name: &'a str,
+    /// to the first file of the group
+    pub distance: u32,
+}
+
+pub fn set_mode(mode: OutputMode) {
+    MODE.set(mode).ok();
+}
+
+pub fn is_json() -> bool {
+    MODE.get() == Some(&OutputMode::Json)
+}
+
+pub fn emit(event: Event) {
+    {
+        let mut summary = SUMMARY.lock().unwrap();
+        match &event {
+            Event::ArtworkResolved { .. } => summary.artworks += 1,
+            Event::FileFinished { bytes, .. } => {
+                summary.files += 1;
+                summary.bytes += bytes;
+            }
+            Event::Skipped { page: None, .. } => summary.skipped += 1,
+            Event::Error { .. } => summary.errors += 1,
+            Event::Duplicate { .. } => summary.duplicates += 1,
+            _ => {}
+        }
+    }
+
+    if is_json() {
+        println!("{}", serde_json::to_string(&event).unwrap());
+    }
+}
+
+/// Reports an error as an event, or prints `message` in human mode.
+pub fn error<E: ErrorKind + Display>(
+    illust_id: Option<usize>,
+    page: Option<usize>,
+    err: &E,
+    message: String,
+) {
+    if !is_json() {
+        println!("{}", message);
+    }
+
+    error_event(illust_id, page, err);
+}
+
+/// Records an error without printing anything in human mode.
+pub fn error_event<E: ErrorKind + Display>(illust_id: Option<usize>, page: Option<usize>, err: &E) {
+    emit(Event::Error {
+        illust_id,
+        page,
+        kind: err.kind(),
+        message: err.to_string(),
+    });
+}
+
+/// Emits the summary and returns the process exit code.
+pub fn finish<E: ErrorKind + Display>(result: Result<(), E>) -> i32 {
+    let code = match &result {
+        Err(err) => {
+            if !is_json() {
+                println!("Error: {}", err);
+            }
+            emit(Event::Error {
+                illust_id: None,
+                page: None,
+                kind: err.kind(),
+                message: err.to_string(),
+            });
+            EXIT_FATAL
+        }
+        Ok(_) if is_interrupted() => EXIT_INTERRUPTED,
+        Ok(_) if SUMMARY.lock().unwrap().errors > 0 => EXIT_PARTIAL,
+        Ok(_) => EXIT_OK,
+    };
+    let summary = *SUMMARY.lock().unwrap();
+
+    emit(Event::Summary(summary));
+
+    code
+}
+
+pub fn set_interrupted() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Emits a progress event roughly every 5% of the file.
+pub fn progress(illust_id: usize, page: usize, last: &mut u64, bytes: u64, total: u64) {
+    let step = (total / 20).max(1);
+    if is_json() && (bytes == total || bytes >= *last + step) {
+        *last = bytes;
+        emit(Event::Progress {
+            illust_id,
+            page,
+            bytes,
+            total,
+        });
+    }
+}
+
+pub fn multi_progress() -> MultiProgress {
+    if is_json() {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    }
+}
+
+pub trait ErrorKind {
+    fn kind(&self) -> &'static str;
+}
+
+impl ErrorKind for x_pixiv_lib::Error {
+    fn kind(&self) -> &'static str {
+        x_pixiv_lib::job::error_kind(self)
+    }
+}
+
+impl ErrorKind for hyper::Error {
+    fn kind(&self) -> &'static str {
+        "server"
+    }
+}
+
+impl ErrorKind for std::io::Error {
+    fn kind(&self) -> &'static str {
+        "io"
+    }
+}
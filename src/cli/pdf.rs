@@ -0,0 +1,60 @@
+use std::{io, path::Path};
+
+use clap::Args;
+use x_pixiv_lib::pdf::{folder_pdfs, write_pdf};
+
+use super::output::{self, Event};
+
+#[derive(Args)]
+pub struct PdfArgs {
+    /// downloaded folder with sidecars
+    path: String,
+
+    /// write every PDF again, even if it is newer than its pages
+    #[arg(default_value_t = false, short = 'f', long)]
+    force: bool,
+}
+
+/// Writes a PDF per series and per artwork with several pages in a downloaded folder.
+///
+/// Artworks are found by their sidecars, so the folder has to be downloaded with
+/// `--sidecar`. Series episodes go into one PDF, in order, next to the first episode.
+pub async fn pdf(args: &PdfArgs) -> x_pixiv_lib::Result<()> {
+    if let Err(err) = run(args) {
+        output::error(None, None, &err, format!("{}: {}", args.path, err));
+    }
+
+    Ok(())
+}
+
+fn run(args: &PdfArgs) -> io::Result<()> {
+    let mut written = 0;
+
+    for pdf in folder_pdfs(Path::new(&args.path))? {
+        if !args.force && !pdf.outdated() {
+            continue;
+        }
+        let path = pdf.path.to_string_lossy();
+        let pages = pdf.works.iter().map(|work| work.pages.len()).sum();
+
+        if let Err(err) = write_pdf(&pdf.path, &pdf.works) {
+            output::error(None, None, &err, format!("{}: {}", path, err));
+            continue;
+        }
+        written += 1;
+        if !output::is_json() {
+            println!("{}: {} artworks, {} pages", path, pdf.works.len(), pages);
+        }
+        output::emit(Event::PdfWritten {
+            path: &path,
+            artworks: pdf.works.len(),
+            pages,
+        });
+    }
+
+    if !output::is_json() {
+        println!("{} PDFs written", written);
+    }
+
+    Ok(())
+}
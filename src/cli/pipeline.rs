@@ -0,0 +1,401 @@
+use std::{collections::HashMap, sync::OnceLock, time::Duration};
+
+use clap::Args;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use tokio::{
+    signal::ctrl_c,
+    sync::mpsc::UnboundedReceiver,
+    task::{JoinHandle, JoinSet},
+};
+use x_pixiv_lib::{
+    archive::SharedArchive,
+    control::Control,
+    job::{ArtworkFiles, Job, JobEvent, JobKind, JobManager, JobSummary, ManagerOptions},
+};
+
+use super::output::{self, Event};
+
+static ARCHIVE: OnceLock<SharedArchive> = OnceLock::new();
+
+#[derive(Args, Debug)]
+pub struct ConcurrencyArgs {
+    /// number of artworks whose metadata is fetched at the same time
+    #[arg(default_value_t = 4, short = 'j', long)]
+    pub jobs: usize,
+
+    /// number of files downloaded at the same time
+    #[arg(default_value_t = 8, short = 'c', long)]
+    pub connections: usize,
+
+    /// minimum milliseconds between two metadata requests
+    #[arg(default_value_t = 0, long)]
+    pub rate_limit: u64,
+
+    /// number of images converted at the same time with --convert
+    #[arg(default_value_t = 2, long)]
+    pub converters: usize,
+}
+
+impl From<&ConcurrencyArgs> for ManagerOptions {
+    fn from(args: &ConcurrencyArgs) -> Self {
+        Self {
+            jobs: args.jobs,
+            connections: args.connections,
+            rate_limit: (args.rate_limit > 0).then(|| Duration::from_millis(args.rate_limit)),
+            archive: None,
+            converters: args.converters,
+        }
+    }
+}
+
+/// Runs jobs on the library job manager and shows their events as
+/// progress bars or JSON lines.
+///
+/// The first Ctrl-C cancels the downloads, the second one exits right away.
+pub struct Pipeline {
+    manager: JobManager,
+    control: Control,
+    view: JoinHandle<()>,
+}
+
+/// Records the downloads of every pipeline in `archive`.
+pub fn set_archive(archive: SharedArchive) {
+    ARCHIVE.set(archive).ok();
+}
+
+/// The archive of the `--archive` option.
+pub fn archive() -> Option<SharedArchive> {
+    ARCHIVE.get().cloned()
+}
+
+impl Pipeline {
+    pub fn new(mut options: ManagerOptions) -> Self {
+        if options.archive.is_none() {
+            options.archive = archive();
+        }
+        let (manager, events) = JobManager::new(options);
+        let control = manager.job_control();
+
+        tokio::spawn(cancel_on_ctrl_c(control.clone()));
+
+        Self {
+            manager,
+            control,
+            view: tokio::spawn(show(events)),
+        }
+    }
+
+    pub async fn run(&self, job: Job) -> x_pixiv_lib::Result<JobSummary> {
+        self.manager.run_with(job, self.control.clone()).await
+    }
+
+    /// Runs the jobs at the same time, the results are in the order of `jobs`.
+    pub async fn run_all(&self, jobs: Vec<Job>) -> Vec<(JobKind, x_pixiv_lib::Result<JobSummary>)> {
+        let mut tasks = JoinSet::new();
+        let count = jobs.len();
+
+        for (index, job) in jobs.into_iter().enumerate() {
+            let manager = self.manager.clone();
+            let control = self.control.clone();
+            tasks.spawn(async move {
+                let kind = job.kind.clone();
+                (index, kind, manager.run_with(job, control).await)
+            });
+        }
+
+        let mut results = Vec::with_capacity(count);
+        while let Some(result) = tasks.join_next().await {
+            if let Ok(result) = result {
+                results.push(result);
+            }
+        }
+        results.sort_by_key(|(index, _, _)| *index);
+
+        results
+            .into_iter()
+            .map(|(_, kind, result)| (kind, result))
+            .collect()
+    }
+
+    pub async fn resolve(&self, job: &Job) -> x_pixiv_lib::Result<Vec<ArtworkFiles>> {
+        self.manager.resolve(job).await
+    }
+
+    /// Resolves the jobs at the same time, the results are in the order of `jobs`.
+    pub async fn resolve_all(
+        &self,
+        jobs: &[Job],
+    ) -> Vec<(JobKind, x_pixiv_lib::Result<Vec<ArtworkFiles>>)> {
+        let mut tasks = JoinSet::new();
+
+        for (index, job) in jobs.iter().cloned().enumerate() {
+            let manager = self.manager.clone();
+            tasks.spawn(async move { (index, job.kind.clone(), manager.resolve(&job).await) });
+        }
+
+        let mut results = Vec::with_capacity(jobs.len());
+        while let Some(result) = tasks.join_next().await {
+            if let Ok(result) = result {
+                results.push(result);
+            }
+        }
+        results.sort_by_key(|(index, _, _)| *index);
+
+        results
+            .into_iter()
+            .map(|(_, kind, result)| (kind, result))
+            .collect()
+    }
+
+    pub async fn content_lengths(&self, urls: &[String]) -> Vec<Option<u64>> {
+        self.manager.content_lengths(urls).await
+    }
+
+    /// Waits until every event is shown.
+    pub async fn finish(self) {
+        drop(self.manager);
+        self.view.await.ok();
+    }
+}
+
+async fn cancel_on_ctrl_c(control: Control) {
+    if ctrl_c().await.is_ok() {
+        output::set_interrupted();
+        control.cancel();
+    }
+    if ctrl_c().await.is_ok() {
+        std::process::exit(output::EXIT_INTERRUPTED);
+    }
+}
+
+struct FileView {
+    // added once the size is known
+    bar: Option<ProgressBar>,
+    last_progress: u64,
+}
+
+async fn show(mut events: UnboundedReceiver<JobEvent>) {
+    let progress_manager = output::multi_progress();
+    progress_manager.set_alignment(indicatif::MultiProgressAlignment::Bottom);
+    let mut total_progress: Option<ProgressBar> = None;
+    let mut titles = HashMap::new();
+    let mut files: HashMap<(usize, usize), FileView> = HashMap::new();
+
+    while let Some(event) = events.recv().await {
+        match event {
+            JobEvent::ArtworkQueued { .. } => {
+                let total = total_progress.get_or_insert_with(|| total_bar(&progress_manager));
+                total.inc_length(1);
+            }
+            JobEvent::Skipped {
+                illust_id, reason, ..
+            } => {
+                output::emit(Event::Skipped {
+                    illust_id,
+                    page: None,
+                    reason,
+                });
+            }
+            JobEvent::ArtworkResolved {
+                illust_id,
+                title,
+                user_name,
+                pages,
+                ..
+            } => {
+                output::emit(Event::ArtworkResolved {
+                    illust_id,
+                    title: &title,
+                    user_name: &user_name,
+                    pages,
+                });
+                titles.insert(illust_id, title);
+            }
+            JobEvent::FileStarted {
+                illust_id,
+                page,
+                url,
+                path,
+                ..
+            } => {
+                output::emit(Event::FileStarted {
+                    illust_id,
+                    page,
+                    url: &url,
+                    path: &path.to_string_lossy(),
+                });
+                files.insert(
+                    (illust_id, page),
+                    FileView {
+                        bar: None,
+                        last_progress: 0,
+                    },
+                );
+            }
+            JobEvent::Progress {
+                illust_id,
+                page,
+                bytes,
+                total,
+                ..
+            } => {
+                if let Some(file) = files.get_mut(&(illust_id, page)) {
+                    let bar = file.bar.get_or_insert_with(|| {
+                        let bar = file_bar(&progress_manager, total);
+                        bar.set_message(format!(
+                            "{}-{}",
+                            titles.get(&illust_id).cloned().unwrap_or_default(),
+                            page
+                        ));
+                        bar
+                    });
+                    bar.set_position(bytes);
+                    output::progress(illust_id, page, &mut file.last_progress, bytes, total);
+                }
+            }
+            JobEvent::FileFinished {
+                illust_id,
+                page,
+                path,
+                bytes,
+                sha256,
+                ..
+            } => {
+                output::emit(Event::FileFinished {
+                    illust_id,
+                    page,
+                    path: &path.to_string_lossy(),
+                    bytes,
+                    sha256: &sha256,
+                });
+                if let Some(bar) = files.remove(&(illust_id, page)).and_then(|file| file.bar) {
+                    bar.finish_and_clear();
+                }
+            }
+            // one line per page would bury the progress bars
+            JobEvent::Converted {
+                illust_id,
+                page,
+                path,
+                bytes,
+                original,
+                original_bytes,
+                original_kept,
+                ..
+            } => output::emit(Event::Converted {
+                illust_id,
+                page,
+                path: &path.to_string_lossy(),
+                bytes,
+                original: &original.to_string_lossy(),
+                original_bytes,
+                original_kept,
+            }),
+            JobEvent::Duplicate {
+                illust_id,
+                page,
+                path,
+                duplicate_of,
+                duplicate_page,
+                distance,
+                removed,
+                ..
+            } => {
+                if !output::is_json() {
+                    progress_manager.suspend(|| {
+                        println!(
+                            "{} p{} looks like {} p{} (distance {}){}",
+                            illust_id,
+                            page,
+                            duplicate_of,
+                            duplicate_page,
+                            distance,
+                            if removed { ", removed" } else { "" }
+                        )
+                    });
+                }
+                output::emit(Event::Duplicate {
+                    illust_id,
+                    page,
+                    path: &path.to_string_lossy(),
+                    duplicate_of,
+                    duplicate_page,
+                    distance,
+                    removed,
+                });
+            }
+            JobEvent::ArtworkFinished { illust_id, .. } => {
+                titles.remove(&illust_id);
+                if let Some(total) = &total_progress {
+                    total.inc(1);
+                }
+            }
+            JobEvent::Error {
+                illust_id,
+                page,
+                kind,
+                message,
+                ..
+            } => {
+                if !output::is_json() {
+                    // printed above the progress bars instead of through them
+                    progress_manager
+                        .suspend(|| println!("{} {}", illust_id.unwrap_or_default(), message));
+                }
+                output::emit(Event::Error {
+                    illust_id,
+                    page,
+                    kind,
+                    message,
+                });
+                if let Some(bar) = page
+                    .and_then(|page| files.remove(&(illust_id?, page)))
+                    .and_then(|file| file.bar)
+                {
+                    bar.finish_and_clear();
+                }
+            }
+            JobEvent::Cancelled {
+                illust_id, page, ..
+            } => {
+                output::emit(Event::Skipped {
+                    illust_id,
+                    page,
+                    reason: "cancelled",
+                });
+                if let Some(bar) = page
+                    .and_then(|page| files.remove(&(illust_id, page)))
+                    .and_then(|file| file.bar)
+                {
+                    bar.finish_and_clear();
+                }
+            }
+            JobEvent::JobFinished { .. } => {}
+        }
+    }
+
+    if let Some(total) = total_progress {
+        total.finish_with_message("Done");
+    }
+}
+
+fn total_bar(progress_manager: &MultiProgress) -> ProgressBar {
+    let total_progress = progress_manager.add(ProgressBar::new(0));
+    total_progress.set_style(
+        ProgressStyle::with_template(
+            "{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} ({eta})",
+        )
+        .unwrap()
+        .progress_chars("##-"),
+    );
+    total_progress.enable_steady_tick(Duration::from_millis(100));
+    total_progress
+}
+
+fn file_bar(progress_manager: &MultiProgress, total: u64) -> ProgressBar {
+    let progress = progress_manager.add(ProgressBar::new(total));
+    progress.set_style(ProgressStyle::with_template("{spinner:.green} [{msg}] [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+        .unwrap()
+        .progress_chars("#>-"));
+    progress
+}
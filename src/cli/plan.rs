@@ -0,0 +1,149 @@
+use clap::{Args, ValueEnum};
+use indicatif::HumanBytes;
+use serde::Serialize;
+use x_pixiv_lib::job::{ArtworkFiles, JobOptions};
+
+use super::pipeline::Pipeline;
+
+#[derive(Args, Debug)]
+pub struct DryRunArgs {
+    /// resolve everything and print the download plan without writing files
+    #[arg(default_value_t = false, long)]
+    pub dry_run: bool,
+
+    /// dry run output format
+    #[arg(value_enum, default_value_t = PlanFormat::Table, long)]
+    pub plan_format: PlanFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PlanFormat {
+    Table,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Plan {
+    artworks: usize,
+    files: usize,
+    existing: usize,
+    /// sum of the known download sizes in bytes
+    total_size: u64,
+    entries: Vec<PlanEntry>,
+}
+
+#[derive(Serialize)]
+struct PlanEntry {
+    illust_id: usize,
+    title: String,
+    page: usize,
+    url: String,
+    /// where the page is saved, with the extension of `--convert`
+    path: String,
+    /// `.pdf` and `.cbz` the page is packed into, a `.cbz` replaces the page
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    packages: Vec<String>,
+    /// whether the page, or the `.cbz` it is packed into, already exists
+    exists: bool,
+    /// size of the download
+    size: Option<u64>,
+}
+
+/// Prints where the pages of `artworks` go, their sizes are fetched at the same time up
+/// to the connections of `pipeline`.
+pub async fn print_plan(
+    pipeline: &Pipeline,
+    artworks: Vec<(ArtworkFiles, &JobOptions)>,
+    format: PlanFormat,
+) -> x_pixiv_lib::Result<()> {
+    let urls = artworks
+        .iter()
+        .flat_map(|(artwork, _)| artwork.files.iter().map(|(url, _)| url.clone()))
+        .collect::<Vec<_>>();
+    let mut sizes = pipeline.content_lengths(&urls).await.into_iter();
+    let mut entries = vec![];
+
+    for (artwork, options) in &artworks {
+        let artwork = converted(artwork, options);
+        let (pdf, cbz) = artwork.package_paths(options);
+        let packages = pdf
+            .iter()
+            .chain(&cbz)
+            .map(|path| path.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+
+        for (page, (url, path)) in artwork.files.iter().enumerate() {
+            entries.push(PlanEntry {
+                illust_id: artwork.data.id,
+                title: artwork.data.title.clone(),
+                page,
+                url: url.clone(),
+                path: path.to_string_lossy().to_string(),
+                packages: packages.clone(),
+                exists: cbz.as_ref().unwrap_or(path).exists(),
+                size: sizes.next().flatten(),
+            });
+        }
+    }
+
+    let plan = Plan {
+        artworks: artworks.len(),
+        files: entries.len(),
+        existing: entries.iter().filter(|entry| entry.exists).count(),
+        total_size: entries.iter().filter_map(|entry| entry.size).sum(),
+        entries,
+    };
+
+    match format {
+        PlanFormat::Json => println!("{}", serde_json::to_string_pretty(&plan).unwrap()),
+        PlanFormat::Table => {
+            println!(
+                "{:<10} {:>4} {:>10} {:<6} path",
+                "id", "page", "size", "exists"
+            );
+            for entry in &plan.entries {
+                println!(
+                    "{:<10} {:>4} {:>10} {:<6} {}",
+                    entry.illust_id,
+                    entry.page,
+                    entry
+                        .size
+                        .map(|size| HumanBytes(size).to_string())
+                        .unwrap_or_else(|| "?".to_string()),
+                    if entry.exists { "yes" } else { "no" },
+                    if entry.packages.is_empty() {
+                        entry.path.clone()
+                    } else {
+                        format!(
+                            "{}, packaged into {}",
+                            entry.path,
+                            entry.packages.join(", ")
+                        )
+                    }
+                );
+            }
+            println!(
+                "{} artworks, {} files ({} already exist), about {}",
+                plan.artworks,
+                plan.files,
+                plan.existing,
+                HumanBytes(plan.total_size)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// `artwork` with the paths its pages have after `--convert`.
+fn converted(artwork: &ArtworkFiles, options: &JobOptions) -> ArtworkFiles {
+    let mut artwork = artwork.clone();
+
+    if let Some(convert) = &options.convert {
+        for (_, path) in artwork.files.iter_mut() {
+            *path = convert.converted_path(path);
+        }
+    }
+
+    artwork
+}
@@ -0,0 +1,187 @@
+use std::{collections::HashSet, io, path::Path};
+
+use clap::{Args, Subcommand};
+use x_pixiv_lib::{
+    job::Job,
+    queue::{JobQueue, QueueEntry},
+};
+
+use super::{
+    output,
+    pipeline::{ConcurrencyArgs, Pipeline},
+    ArtworkArgs, RankArgs, SearchArgs, UserArgs,
+};
+
+#[derive(Args)]
+pub struct QueueArgs {
+    /// queue file, shared with the TUI
+    #[arg(default_value_t = String::from("./queue.toml"), short = 'f', long)]
+    file: String,
+
+    #[command(subcommand)]
+    command: QueueCommands,
+}
+
+#[derive(Subcommand)]
+enum QueueCommands {
+    /// show the queued jobs
+    List,
+    /// queue a download with the same options as the download command
+    #[command(subcommand)]
+    Add(Box<AddCommands>),
+    /// remove jobs from the queue
+    Remove {
+        /// queue entry ids
+        #[arg(required = true)]
+        ids: Vec<usize>,
+    },
+    /// download every queued job, finished jobs are removed
+    Run(ConcurrencyArgs),
+}
+
+#[derive(Subcommand)]
+enum AddCommands {
+    Rank(RankArgs),
+    Artwork(ArtworkArgs),
+    User(UserArgs),
+    Search(SearchArgs),
+}
+
+impl AddCommands {
+    fn jobs(&self) -> io::Result<Vec<Job>> {
+        match self {
+            AddCommands::Rank(args) => Ok(vec![args.job()]),
+            AddCommands::Artwork(args) => args.jobs(),
+            AddCommands::User(args) => args.jobs(),
+            AddCommands::Search(args) => Ok(vec![args.job()]),
+        }
+    }
+}
+
+pub async fn queue_command(args: &QueueArgs) -> x_pixiv_lib::Result<()> {
+    let path = Path::new(&args.file);
+    let result = match &args.command {
+        QueueCommands::List => JobQueue::load(path).map(|queue| print_entries(&queue.entries)),
+        QueueCommands::Add(command) => match command.jobs() {
+            Ok(jobs) => add(path, jobs),
+            Err(err) => {
+                // the error names the input file
+                output::error(None, None, &err, err.to_string());
+                return Ok(());
+            }
+        },
+        QueueCommands::Remove { ids } => remove(path, ids),
+        QueueCommands::Run(concurrency) => run(path, concurrency).await,
+    };
+
+    if let Err(err) = result {
+        output::error(None, None, &err, format!("{}: {}", args.file, err));
+    }
+
+    Ok(())
+}
+
+fn add(path: &Path, jobs: Vec<Job>) -> io::Result<()> {
+    let mut queue = JobQueue::load(path)?;
+    let ids = jobs
+        .into_iter()
+        .map(|job| queue.push(job))
+        .collect::<Vec<_>>();
+
+    queue.save(path)?;
+    print_entries(queue.entries.iter().filter(|entry| ids.contains(&entry.id)));
+
+    Ok(())
+}
+
+fn remove(path: &Path, ids: &[usize]) -> io::Result<()> {
+    let mut queue = JobQueue::load(path)?;
+
+    for id in ids {
+        if queue.remove(*id).is_none() && !output::is_json() {
+            println!("no queue entry {}", id);
+        }
+    }
+
+    queue.save(path)
+}
+
+/// Runs the queued jobs one after another.
+///
+/// The file is read again before and after every job, so entries added or removed
+/// meanwhile (e.g. by the TUI) are respected. A job that fails stays queued with its
+/// error, a job interrupted with Ctrl-C or with cancelled files stays queued unchanged.
+async fn run(path: &Path, concurrency: &ConcurrencyArgs) -> io::Result<()> {
+    let pipeline = Pipeline::new(concurrency.into());
+    let mut tried = HashSet::new();
+    let mut lines = vec![];
+
+    while let Some(entry) = JobQueue::load(path)?
+        .entries
+        .into_iter()
+        .find(|entry| !tried.contains(&entry.id))
+    {
+        tried.insert(entry.id);
+
+        let result = pipeline.run(entry.job.clone()).await;
+        if output::is_interrupted() {
+            break;
+        }
+
+        let mut queue = JobQueue::load(path)?;
+        match result {
+            Ok(summary) if summary.errors == 0 && summary.cancelled == 0 => {
+                queue.remove(entry.id);
+                lines.push(format!("  + {} {}", entry.id, entry.job.kind));
+            }
+            Ok(summary) if summary.cancelled > 0 => {
+                lines.push(format!("  ~ {} {}", entry.id, entry.job.kind));
+            }
+            Ok(summary) => {
+                queue.fail(
+                    entry.id,
+                    format!("{} artworks or files failed", summary.errors),
+                );
+                lines.push(format!("  ! {} {}", entry.id, entry.job.kind));
+            }
+            Err(err) => {
+                output::error_event(None, None, &err);
+                queue.fail(entry.id, err.to_string());
+                lines.push(format!("  ! {} {}: {}", entry.id, entry.job.kind, err));
+            }
+        }
+        queue.save(path)?;
+    }
+
+    pipeline.finish().await;
+
+    if !output::is_json() {
+        println!("{} queued jobs run", lines.len());
+        lines.iter().for_each(|line| println!("{}", line));
+    }
+
+    Ok(())
+}
+
+fn print_entries<'a>(entries: impl IntoIterator<Item = &'a QueueEntry>) {
+    if output::is_json() {
+        for entry in entries {
+            println!("{}", serde_json::to_string(entry).unwrap());
+        }
+        return;
+    }
+
+    println!("{:>4} {:>8} {:<40} path", "id", "attempts", "job");
+    for entry in entries {
+        println!(
+            "{:>4} {:>8} {:<40} {}",
+            entry.id,
+            entry.attempts,
+            entry.job.kind.to_string(),
+            entry.job.options.output.to_string_lossy()
+        );
+        if let Some(error) = &entry.last_error {
+            println!("{:>14} {}", "", error);
+        }
+    }
+}
@@ -0,0 +1,534 @@
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::Display,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use clap::Args;
+use hyper::{
+    header::{
+        ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+        AUTHORIZATION, CONTENT_TYPE, ORIGIN,
+    },
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::Serialize;
+use serde_json::json;
+use tokio::{signal::ctrl_c, sync::mpsc::UnboundedReceiver};
+use x_pixiv_lib::{
+    archive::SharedArchive,
+    client,
+    control::{Control, ControlState},
+    host,
+    job::{Job, JobEvent, JobId, JobKind, JobManager, JobSummary, ManagerOptions},
+    queue::QueueFile,
+};
+
+use super::{
+    output,
+    pipeline::{self, ConcurrencyArgs},
+    Cli,
+};
+use crate::config::ConfigData;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// address of the HTTP API, other than loopback addresses need --token
+    #[arg(default_value = "127.0.0.1:8080", short = 'l', long)]
+    listen: SocketAddr,
+
+    /// require `Authorization: Bearer TOKEN` on every request, browsers are only let in with it
+    #[arg(long)]
+    token: Option<String>,
+
+    #[command(flatten)]
+    concurrency: ConcurrencyArgs,
+}
+
+/// Jobs started through the API and what they need to run.
+struct State {
+    manager: JobManager,
+    config: ConfigData,
+    queue: QueueFile,
+    archive: Option<SharedArchive>,
+    token: Option<String>,
+    jobs: Mutex<BTreeMap<JobId, ServerJob>>,
+}
+
+#[derive(Serialize, Clone)]
+struct ServerJob {
+    id: JobId,
+    /// id of the job in the queue file
+    queue_entry: usize,
+    kind: JobKind,
+    status: JobStatus,
+    /// counted while the job runs
+    summary: JobSummary,
+    /// files being downloaded
+    files: Vec<FileProgress>,
+    /// why the artwork list could not be fetched
+    error: Option<String>,
+    #[serde(skip)]
+    control: Control,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum JobStatus {
+    Running,
+    Paused,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Clone)]
+struct FileProgress {
+    illust_id: usize,
+    page: usize,
+    bytes: u64,
+    total: u64,
+}
+
+/// Serves the HTTP API until Ctrl-C, jobs that are still running stay in the queue file.
+pub async fn serve(args: &ServeArgs, cli: &Cli) -> x_pixiv_lib::Result<()> {
+    if args.token.is_none() && !args.listen.ip().is_loopback() {
+        clap::Error::raw(
+            clap::error::ErrorKind::MissingRequiredArgument,
+            format!("listening on {} needs a --token\n", args.listen),
+        )
+        .exit();
+    }
+    let config = match ConfigData::load() {
+        Ok(config) => config,
+        Err(err) => {
+            output::error(None, None, &err, err.to_string());
+            return Ok(());
+        }
+    };
+    // command line options win over config.toml
+    if cli.proxy.is_none() && cli.image_proxy.is_none() && cli.no_proxy.is_empty() {
+        client::init(&config.proxy)?;
+    }
+    if cli.image_host.is_empty() {
+        host::set_image_hosts(config.image_hosts.clone());
+    }
+    let archive = match pipeline::archive() {
+        Some(archive) => Some(archive),
+        None => config.open_archive().unwrap_or_else(|err| {
+            output::error(None, None, &err, format!("{}: {}", config.archive, err));
+            None
+        }),
+    };
+    let (manager, events) = JobManager::new(ManagerOptions {
+        archive: archive.clone(),
+        ..(&args.concurrency).into()
+    });
+    let state = Arc::new(State {
+        manager,
+        queue: config.queue_file(),
+        config,
+        archive,
+        token: args.token.clone(),
+        jobs: Mutex::default(),
+    });
+
+    tokio::spawn(track(state.clone(), events));
+    if state.config.resume_queue {
+        match state.queue.update(|queue| queue.entries.clone()) {
+            Ok(entries) => {
+                for entry in entries {
+                    state.start(entry.id, entry.job);
+                }
+            }
+            Err(err) => output::error(
+                None,
+                None,
+                &err,
+                format!("{}: {}", state.queue.path().display(), err),
+            ),
+        }
+    }
+
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |request| state.clone().handle(request))) }
+    });
+    let server = match Server::try_bind(&args.listen) {
+        Ok(server) => server,
+        Err(err) => {
+            output::error(None, None, &err, format!("{}: {}", args.listen, err));
+            return Ok(());
+        }
+    };
+
+    if !output::is_json() {
+        println!("listening on http://{}", args.listen);
+    }
+    if let Err(err) = server
+        .serve(make_service)
+        .with_graceful_shutdown(async {
+            ctrl_c().await.ok();
+        })
+        .await
+    {
+        output::error(None, None, &err, err.to_string());
+    }
+
+    Ok(())
+}
+
+impl State {
+    /// Runs a queued job in the background and returns its id.
+    fn start(self: &Arc<Self>, entry: usize, job: Job) -> JobId {
+        let id = self.manager.next_id();
+        let control = self.manager.job_control();
+        let state = self.clone();
+
+        self.jobs.lock().unwrap().insert(
+            id,
+            ServerJob {
+                id,
+                queue_entry: entry,
+                kind: job.kind.clone(),
+                status: JobStatus::Running,
+                summary: JobSummary::default(),
+                files: vec![],
+                error: None,
+                control: control.clone(),
+            },
+        );
+        if !output::is_json() {
+            println!("job {} started: {}", id, job.kind);
+        }
+
+        tokio::spawn(async move {
+            let result = state.manager.run_as(id, job, control.clone()).await;
+            let finished = if control.is_cancelled() {
+                // cancelled through the API, not wanted anymore
+                state.queue.update(|queue| queue.remove(entry)).map(|_| ())
+            } else {
+                state.queue.finish(entry, &result)
+            };
+            if let Err(err) = finished {
+                output::error(
+                    None,
+                    None,
+                    &err,
+                    format!("{}: {}", state.queue.path().display(), err),
+                );
+            }
+
+            let mut jobs = state.jobs.lock().unwrap();
+            let Some(job) = jobs.get_mut(&id) else {
+                return;
+            };
+            job.status = match &result {
+                _ if control.is_cancelled() => JobStatus::Cancelled,
+                Ok(summary) if summary.errors == 0 => JobStatus::Finished,
+                _ => JobStatus::Failed,
+            };
+            match result {
+                Ok(summary) => job.summary = summary,
+                Err(err) => job.error = Some(err.to_string()),
+            }
+            if !output::is_json() {
+                println!(
+                    "job {} {}: {} files, {} errors",
+                    id, job.status, job.summary.files, job.summary.errors
+                );
+            }
+        });
+
+        id
+    }
+
+    async fn handle(self: Arc<Self>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+        // any web page could start jobs through the browser without a token
+        let cors = self.token.is_some();
+        let mut response = if cors && request.method() == Method::OPTIONS {
+            // CORS preflight of browser bookmarklets
+            empty(StatusCode::NO_CONTENT)
+        } else if !cors && request.headers().contains_key(ORIGIN) {
+            error(StatusCode::FORBIDDEN, "browser requests need --token")
+        } else if !self.authorized(&request) {
+            error(StatusCode::UNAUTHORIZED, "missing or wrong bearer token")
+        } else {
+            self.route(request).await
+        };
+        if !cors {
+            return Ok(response);
+        }
+        let headers = response.headers_mut();
+
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, "*".parse().unwrap());
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_HEADERS,
+            "authorization, content-type".parse().unwrap(),
+        );
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_METHODS,
+            "GET, POST, DELETE, OPTIONS".parse().unwrap(),
+        );
+
+        Ok(response)
+    }
+
+    async fn route(self: Arc<Self>, request: Request<Body>) -> Response<Body> {
+        let method = request.method().clone();
+        let path = request.uri().path().trim_matches('/').to_string();
+        let segments = path.split('/').collect::<Vec<_>>();
+
+        match (method, segments.as_slice()) {
+            (Method::GET, ["api", "jobs"]) => {
+                let jobs = self.jobs.lock().unwrap();
+                json_response(
+                    StatusCode::OK,
+                    &jobs.values().map(ServerJob::view).collect::<Vec<_>>(),
+                )
+            }
+            (Method::POST, ["api", "jobs"]) => {
+                let body = match hyper::body::to_bytes(request.into_body()).await {
+                    Ok(body) => body,
+                    Err(err) => return error(StatusCode::BAD_REQUEST, &err.to_string()),
+                };
+                match serde_json::from_slice::<JobKind>(&body) {
+                    Ok(kind) => {
+                        let job = Job::new(kind, self.config.job_options());
+                        match self.queue.update(|queue| queue.push(job.clone())) {
+                            Ok(entry) => {
+                                let id = self.start(entry, job);
+                                json_response(StatusCode::CREATED, &json!({ "id": id }))
+                            }
+                            Err(err) => error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+                        }
+                    }
+                    Err(err) => error(StatusCode::BAD_REQUEST, &err.to_string()),
+                }
+            }
+            (method, ["api", "jobs", id, action @ ..]) => {
+                let Ok(id) = id.parse::<JobId>() else {
+                    return error(StatusCode::NOT_FOUND, "no such job");
+                };
+                let jobs = self.jobs.lock().unwrap();
+                let Some(job) = jobs.get(&id) else {
+                    return error(StatusCode::NOT_FOUND, "no such job");
+                };
+
+                match (method, action) {
+                    (Method::GET, []) => {}
+                    (Method::DELETE, []) => job.control.cancel(),
+                    (Method::POST, ["pause"]) => job.control.pause(),
+                    (Method::POST, ["resume"]) => job.control.resume(),
+                    _ => return error(StatusCode::NOT_FOUND, "not found"),
+                }
+
+                json_response(StatusCode::OK, &job.view())
+            }
+            (Method::GET, ["api", "queue"]) => {
+                match self.queue.update(|queue| queue.entries.clone()) {
+                    Ok(entries) => json_response(StatusCode::OK, &entries),
+                    Err(err) => error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+                }
+            }
+            (Method::GET, ["api", "archive"]) => match &self.archive {
+                Some(archive) => {
+                    let archive = archive.lock().unwrap();
+                    json_response(StatusCode::OK, &archive.entries().collect::<Vec<_>>())
+                }
+                None => json_response(StatusCode::OK, &json!([])),
+            },
+            (Method::GET, ["api", "archive", id]) => {
+                let entry = id.parse().ok().and_then(|id| {
+                    self.archive
+                        .as_ref()
+                        .and_then(|archive| archive.lock().unwrap().get(id).cloned())
+                });
+                match entry {
+                    Some(entry) => json_response(StatusCode::OK, &entry),
+                    None => error(StatusCode::NOT_FOUND, "not archived"),
+                }
+            }
+            _ => error(StatusCode::NOT_FOUND, "not found"),
+        }
+    }
+
+    fn authorized(&self, request: &Request<Body>) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let given = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+
+        // compare every byte so the time does not tell how much of the token matched
+        given.len() == token.len()
+            && given
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+impl ServerJob {
+    /// The job as returned by the API.
+    fn view(&self) -> Self {
+        let mut job = self.clone();
+        if job.status == JobStatus::Running && job.control.state() == ControlState::Paused {
+            job.status = JobStatus::Paused;
+        }
+        job
+    }
+
+    fn remove_file(&mut self, illust_id: usize, page: usize) {
+        self.files
+            .retain(|file| file.illust_id != illust_id || file.page != page);
+    }
+}
+
+impl Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use JobStatus::*;
+
+        write!(
+            f,
+            "{}",
+            match self {
+                Running => "running",
+                Paused => "paused",
+                Finished => "finished",
+                Failed => "failed",
+                Cancelled => "cancelled",
+            }
+        )
+    }
+}
+
+/// Keeps the progress of the API jobs in sync with the job events.
+async fn track(state: Arc<State>, mut events: UnboundedReceiver<JobEvent>) {
+    while let Some(event) = events.recv().await {
+        let mut jobs = state.jobs.lock().unwrap();
+
+        match event {
+            JobEvent::ArtworkResolved { job, .. } => {
+                if let Some(job) = jobs.get_mut(&job) {
+                    job.summary.artworks += 1;
+                }
+            }
+            JobEvent::FileStarted {
+                job,
+                illust_id,
+                page,
+                ..
+            } => {
+                if let Some(job) = jobs.get_mut(&job) {
+                    job.files.push(FileProgress {
+                        illust_id,
+                        page,
+                        bytes: 0,
+                        total: 0,
+                    });
+                }
+            }
+            JobEvent::Progress {
+                job,
+                illust_id,
+                page,
+                bytes,
+                total,
+            } => {
+                if let Some(file) = jobs.get_mut(&job).and_then(|job| {
+                    job.files
+                        .iter_mut()
+                        .find(|file| file.illust_id == illust_id && file.page == page)
+                }) {
+                    file.bytes = bytes;
+                    file.total = total;
+                }
+            }
+            JobEvent::FileFinished {
+                job,
+                illust_id,
+                page,
+                bytes,
+                ..
+            } => {
+                if let Some(job) = jobs.get_mut(&job) {
+                    job.summary.files += 1;
+                    job.summary.bytes += bytes;
+                    job.remove_file(illust_id, page);
+                }
+            }
+            JobEvent::Error {
+                job,
+                illust_id,
+                page,
+                ..
+            } => {
+                if let Some(job) = jobs.get_mut(&job) {
+                    job.summary.errors += 1;
+                    if let (Some(illust_id), Some(page)) = (illust_id, page) {
+                        job.remove_file(illust_id, page);
+                    }
+                }
+            }
+            JobEvent::Cancelled {
+                job,
+                illust_id,
+                page,
+            } => {
+                if let Some(job) = jobs.get_mut(&job) {
+                    job.summary.cancelled += 1;
+                    if let Some(page) = page {
+                        job.remove_file(illust_id, page);
+                    }
+                }
+            }
+            JobEvent::Skipped { job, .. } => {
+                if let Some(job) = jobs.get_mut(&job) {
+                    job.summary.skipped += 1;
+                }
+            }
+            JobEvent::Duplicate { job, .. } => {
+                if let Some(job) = jobs.get_mut(&job) {
+                    job.summary.duplicates += 1;
+                }
+            }
+            JobEvent::JobFinished { job, summary } => {
+                if let Some(job) = jobs.get_mut(&job) {
+                    job.summary = summary;
+                    job.files.clear();
+                }
+            }
+            JobEvent::ArtworkQueued { .. }
+            | JobEvent::Converted { .. }
+            | JobEvent::ArtworkFinished { .. } => {}
+        }
+    }
+}
+
+fn json_response<T: Serialize + ?Sized>(status: StatusCode, value: &T) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_string(value).unwrap()))
+        .unwrap()
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &json!({ "error": message }))
+}
+
+fn empty(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .unwrap()
+}
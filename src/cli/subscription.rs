@@ -0,0 +1,136 @@
+use std::{
+    fs::read_to_string,
+    io::{self, ErrorKind},
+};
+
+use serde::Deserialize;
+use x_pixiv_lib::{
+    artworks::Quality,
+    filter::Filter,
+    job::{Job, JobKind},
+    sidecar::SidecarFormat,
+};
+
+use super::{
+    group_dir, parse_agrs_type, parse_search_mode, parse_search_order, MetadataArgs, FILE_NAME,
+    GROUPED_FILE_NAME,
+};
+
+#[derive(Deserialize)]
+struct SubscriptionFile {
+    #[serde(default)]
+    subscription: Vec<Subscription>,
+}
+
+/// What a `[[subscription]]` or `[[watch]]` table downloads and where, exactly one of
+/// `rank`, `user`, `search` or `bookmarks` is set.
+#[derive(Deserialize)]
+pub struct Subscription {
+    /// shown in the summary, defaults to the job, e.g. `rank daily 1-500`
+    name: Option<String>,
+    rank: Option<String>,
+    #[serde(default = "default_start")]
+    start: usize,
+    #[serde(default = "default_end")]
+    end: usize,
+    user: Option<usize>,
+    /// keyword or tag
+    search: Option<String>,
+    #[serde(default)]
+    mode: String,
+    #[serde(default)]
+    order: String,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    /// user id whose public bookmarks are downloaded
+    bookmarks: Option<usize>,
+    #[serde(default = "default_path")]
+    path: String,
+    /// `author` or `title`, like `-g`
+    group: Option<String>,
+    /// sub folder template, overrides `group`
+    dir_name: Option<String>,
+    /// file name template
+    file_name: Option<String>,
+    #[serde(default)]
+    quality: Quality,
+    /// overrides `--sidecar`
+    sidecar: Option<SidecarFormat>,
+    #[serde(flatten)]
+    filter: Filter,
+}
+
+impl Subscription {
+    /// The entry name and its job, artworks in the archive are skipped.
+    pub fn into_job(self, metadata: &MetadataArgs) -> Result<(String, Job), String> {
+        let (kind, file_name) = match (self.rank, self.user, self.search, self.bookmarks) {
+            (Some(rank_type), None, None, None) => (
+                JobKind::Rank {
+                    rank_type: parse_agrs_type(&rank_type),
+                    range: self.start..self.end,
+                    date: None,
+                },
+                GROUPED_FILE_NAME,
+            ),
+            (None, Some(user_id), None, None) => (JobKind::User(user_id), FILE_NAME),
+            (None, None, Some(word), None) => (
+                JobKind::Search {
+                    word,
+                    mode: parse_search_mode(&self.mode),
+                    order: parse_search_order(&self.order),
+                    limit: self.limit,
+                },
+                GROUPED_FILE_NAME,
+            ),
+            (None, None, None, Some(user_id)) => (JobKind::Bookmarks(user_id), GROUPED_FILE_NAME),
+            _ => return Err("set exactly one of rank, user, search or bookmarks".to_string()),
+        };
+        let name = self.name.unwrap_or_else(|| kind.to_string());
+        let dir_name = self
+            .dir_name
+            .unwrap_or_else(|| group_dir(&self.group).to_string());
+        let file_name = self.file_name.as_deref().unwrap_or(file_name);
+        let mut options = metadata.job_options(&self.path, &dir_name, file_name);
+
+        options.skip_archived = true;
+        options.quality = self.quality;
+        if !self.filter.is_empty() {
+            self.filter
+                .validate()
+                .map_err(|err| format!("{}: {}", name, err))?;
+            options.filter = self.filter;
+        }
+        if self.sidecar.is_some() {
+            options.sidecar = self.sidecar;
+        }
+
+        Ok((name, Job::new(kind, options)))
+    }
+}
+
+pub fn load_subscriptions(path: &str, metadata: &MetadataArgs) -> io::Result<Vec<(String, Job)>> {
+    let file = toml::from_str::<SubscriptionFile>(&read_to_string(path)?)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+    file.subscription
+        .into_iter()
+        .map(|subscription| subscription.into_job(metadata))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+}
+
+fn default_start() -> usize {
+    1
+}
+
+fn default_end() -> usize {
+    500
+}
+
+fn default_limit() -> usize {
+    60
+}
+
+fn default_path() -> String {
+    "./".to_string()
+}
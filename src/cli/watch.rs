@@ -0,0 +1,211 @@
+use std::{
+    collections::BTreeMap,
+    fs::{read_to_string, write},
+    io::{self, ErrorKind},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, TimeZone};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use x_pixiv_lib::{archive::Archive, job::Job, schedule::Schedule};
+
+use super::{
+    output::{self, Event},
+    pipeline::{self, ConcurrencyArgs, Pipeline},
+    subscription::Subscription,
+    MetadataArgs,
+};
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// schedule file
+    #[arg(default_value_t = String::from("./watch.toml"))]
+    schedule: String,
+
+    /// last run of every entry, used to catch up on runs missed while x-pixiv was not running
+    #[arg(default_value_t = String::from("./watch-state.toml"), short = 's', long)]
+    state: String,
+
+    /// run the entries that are due and exit, e.g. when started by cron or at boot
+    #[arg(default_value_t = false, long)]
+    once: bool,
+
+    #[command(flatten)]
+    concurrency: ConcurrencyArgs,
+
+    #[command(flatten)]
+    metadata: MetadataArgs,
+}
+
+#[derive(Deserialize)]
+struct ScheduleFile {
+    #[serde(default)]
+    watch: Vec<WatchEntry>,
+}
+
+/// One `[[watch]]` table, a subscription with one of `every` or `cron`.
+#[derive(Deserialize)]
+struct WatchEntry {
+    #[serde(flatten)]
+    subscription: Subscription,
+    every: Option<String>,
+    cron: Option<String>,
+}
+
+/// A schedule file entry ready to run.
+struct Watch {
+    name: String,
+    schedule: Schedule,
+    job: Job,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct WatchState {
+    /// unix timestamp in seconds by entry name
+    #[serde(default)]
+    last_run: BTreeMap<String, i64>,
+}
+
+/// Runs the entries of the schedule file when they are due, until Ctrl-C.
+///
+/// Artworks in the archive are skipped, without `--archive` it is `./archive.jsonl`.
+pub async fn watch(args: &WatchArgs) -> x_pixiv_lib::Result<()> {
+    if let Err(err) = run(args).await {
+        output::error(None, None, &err, format!("{}: {}", args.schedule, err));
+    }
+
+    Ok(())
+}
+
+async fn run(args: &WatchArgs) -> io::Result<()> {
+    let watches = load_schedule(&args.schedule, &args.metadata)?;
+    let mut state = load_state(&args.state)?;
+
+    if pipeline::archive().is_none() {
+        let archive = Archive::open("./archive.jsonl")?;
+        pipeline::set_archive(Arc::new(Mutex::new(archive)));
+    }
+
+    let pipeline = Pipeline::new((&args.concurrency).into());
+
+    loop {
+        for watch in &watches {
+            if output::is_interrupted() {
+                break;
+            }
+            let last = state
+                .last_run
+                .get(&watch.name)
+                .and_then(|last| Local.timestamp_opt(*last, 0).single());
+            let due = match last {
+                None => true,
+                // also true once for every entry missed while x-pixiv was not running
+                Some(last) => watch
+                    .schedule
+                    .next_after(last)
+                    .is_some_and(|next| next <= Local::now()),
+            };
+            if !due {
+                continue;
+            }
+
+            let result = pipeline.run(watch.job.clone()).await;
+            state
+                .last_run
+                .insert(watch.name.clone(), Local::now().timestamp());
+            write(&args.state, toml::to_string(&state).unwrap())?;
+
+            match result {
+                Ok(summary) => {
+                    if !output::is_json() {
+                        println!(
+                            "{} {}: {} artworks, {} files, {} skipped, {} errors",
+                            Local::now().format("%Y-%m-%d %H:%M"),
+                            watch.name,
+                            summary.artworks,
+                            summary.files,
+                            summary.skipped,
+                            summary.errors
+                        );
+                    }
+                    output::emit(Event::WatchRun {
+                        name: &watch.name,
+                        artworks: summary.artworks,
+                        files: summary.files,
+                        skipped: summary.skipped,
+                        errors: summary.errors,
+                    });
+                }
+                Err(err) => output::error(None, None, &err, format!("{}: {}", watch.name, err)),
+            }
+        }
+
+        if args.once || output::is_interrupted() {
+            break;
+        }
+        let Some(next) = next_run(&watches, &state) else {
+            break;
+        };
+        while Local::now() < next && !output::is_interrupted() {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    pipeline.finish().await;
+
+    Ok(())
+}
+
+/// The earliest time an entry is due.
+fn next_run(watches: &[Watch], state: &WatchState) -> Option<DateTime<Local>> {
+    watches
+        .iter()
+        .filter_map(|watch| {
+            let last = Local
+                .timestamp_opt(*state.last_run.get(&watch.name)?, 0)
+                .single()?;
+            watch.schedule.next_after(last)
+        })
+        .min()
+}
+
+fn load_schedule(path: &str, metadata: &MetadataArgs) -> io::Result<Vec<Watch>> {
+    let file = toml::from_str::<ScheduleFile>(&read_to_string(path)?)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+    file.watch
+        .into_iter()
+        .map(|entry| entry.into_watch(metadata))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+}
+
+fn load_state(path: &str) -> io::Result<WatchState> {
+    match read_to_string(path) {
+        Ok(content) => {
+            toml::from_str(&content).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(WatchState::default()),
+        Err(err) => Err(err),
+    }
+}
+
+impl WatchEntry {
+    fn into_watch(self, metadata: &MetadataArgs) -> Result<Watch, String> {
+        let (name, job) = self.subscription.into_job(metadata)?;
+        let schedule = match (self.every, self.cron) {
+            (Some(every), None) => Schedule::every(&every),
+            (None, Some(cron)) => Schedule::cron(&cron),
+            _ => Err("set either every or cron".to_string()),
+        }
+        .map_err(|err| format!("{}: {}", name, err))?;
+
+        Ok(Watch {
+            name,
+            schedule,
+            job,
+        })
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    fmt::Display,
+    fs::{read_to_string, File},
+    io::{self, ErrorKind, Write},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use x_pixiv_lib::{
+    archive::{Archive, SharedArchive},
+    client::ProxySettings,
+    convert::ConvertOptions,
+    dupes::{DupeAction, DEFAULT_DISTANCE},
+    filter::Filter,
+    host::ImageHost,
+    job::JobOptions,
+    queue::QueueFile,
+    sidecar::SidecarFormat,
+};
+
+/// Settings shared by the TUI and the `serve` command.
+const CONFIG_FILE: &str = "./config.toml";
+/// Queue file shared by the TUI, `serve` and the `queue` command.
+pub const QUEUE_FILE: &str = "./queue.toml";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum GroupType {
+    Artwork,
+    Author,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ConfigData {
+    pub output: String,
+    pub group_type: Option<GroupType>,
+    #[serde(default)]
+    pub sidecar: Option<SidecarFormat>,
+    #[serde(default)]
+    pub embed_metadata: bool,
+    #[serde(default)]
+    pub resume: bool,
+    #[serde(default)]
+    pub resume_queue: bool,
+    #[serde(default)]
+    pub skip_archived: bool,
+    #[serde(default)]
+    pub proxy: ProxySettings,
+    #[serde(default)]
+    pub image_hosts: Vec<ImageHost>,
+    /// empty disables the archive
+    #[serde(default = "default_archive")]
+    pub archive: String,
+    #[serde(default)]
+    pub filter: Filter,
+    #[serde(default)]
+    pub convert: Option<ConvertOptions>,
+    #[serde(default)]
+    pub dupes: Option<DupeAction>,
+    #[serde(default = "default_dupe_distance")]
+    pub dupe_distance: u32,
+    #[serde(default)]
+    pub cbz: bool,
+    #[serde(default)]
+    pub pdf: bool,
+}
+
+impl ConfigData {
+    /// Reads `config.toml`, or writes the default config when there is none.
+    pub fn load() -> io::Result<Self> {
+        let error = |err: &dyn Display| {
+            io::Error::new(ErrorKind::InvalidData, format!("{}: {}", CONFIG_FILE, err))
+        };
+
+        match read_to_string(CONFIG_FILE) {
+            Ok(content) => {
+                let config = toml::from_str::<ConfigData>(&content).map_err(|err| error(&err))?;
+                config.filter.validate().map_err(|err| error(&err))?;
+                Ok(config)
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(ConfigData::default().save()),
+            Err(err) => Err(error(&err)),
+        }
+    }
+
+    pub fn save(&self) -> Self {
+        let mut file = File::create(CONFIG_FILE).unwrap();
+        let toml_str = toml::to_string(self).unwrap();
+        file.write_all(toml_str.as_bytes()).unwrap();
+        self.clone()
+    }
+
+    pub fn job_options(&self) -> JobOptions {
+        let dir_name = match self.group_type {
+            Some(GroupType::Artwork) => "{title}-{illust_id}",
+            Some(GroupType::Author) => "{user_name}",
+            None => "",
+        };
+
+        JobOptions {
+            output: self.output.clone().into(),
+            dir_name: dir_name.to_string(),
+            sidecar: self.sidecar,
+            embed_metadata: self.embed_metadata,
+            resume: self.resume,
+            skip_archived: self.skip_archived,
+            filter: self.filter.clone(),
+            convert: self.convert.clone(),
+            dupes: self.dupes,
+            dupe_distance: self.dupe_distance,
+            cbz: self.cbz,
+            pdf: self.pdf,
+            ..Default::default()
+        }
+    }
+
+    pub fn open_archive(&self) -> io::Result<Option<SharedArchive>> {
+        if self.archive.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Arc::new(Mutex::new(Archive::open(&self.archive)?))))
+    }
+
+    pub fn queue_file(&self) -> QueueFile {
+        QueueFile::new(QUEUE_FILE)
+    }
+}
+
+fn default_archive() -> String {
+    "./archive.jsonl".to_string()
+}
+
+fn default_dupe_distance() -> u32 {
+    DEFAULT_DISTANCE
+}
+
+impl Default for ConfigData {
+    fn default() -> Self {
+        Self {
+            output: "./images".to_string(),
+            group_type: None,
+            sidecar: None,
+            embed_metadata: false,
+            resume: false,
+            resume_queue: false,
+            skip_archived: false,
+            proxy: ProxySettings::default(),
+            image_hosts: vec![],
+            archive: default_archive(),
+            filter: Filter::default(),
+            convert: None,
+            dupes: None,
+            dupe_distance: DEFAULT_DISTANCE,
+            cbz: false,
+            pdf: false,
+        }
+    }
+}
+
+impl Display for GroupType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use GroupType::*;
+
+        write!(
+            f,
+            "{}",
+            match self {
+                Author => "author",
+                Artwork => "artwork",
+            }
+        )
+    }
+}
@@ -1,49 +1,91 @@
 #[cfg(feature = "cli")]
 use clap::Parser;
 #[cfg(feature = "cli")]
-use cli::{artwork_download, rank_downloader, user_download, Cli, Commands};
+use cli::{
+    artwork_download, dupes::dupes, gallery::gallery, get_download, output, pdf::pdf,
+    queue::queue_command, rank_downloader, search_download, serve::serve, sync_download,
+    user_download, watch::watch, Cli, Commands,
+};
+#[cfg(feature = "tui")]
+use config::ConfigData;
 #[cfg(feature = "tui")]
 use crossterm::{
-    event::{read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        read, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+#[cfg(feature = "cli")]
+use std::sync::{Arc, Mutex};
 #[cfg(feature = "tui")]
 use std::{io, time::Duration};
 #[cfg(feature = "tui")]
 use tui::{backend::CrosstermBackend, widgets::ListItem, Terminal};
 #[cfg(feature = "tui")]
 use tui_util::AppState;
+#[cfg(feature = "cli")]
+use x_pixiv_lib::archive::Archive;
 
 #[cfg(feature = "cli")]
 mod cli;
+mod config;
 #[cfg(feature = "tui")]
 mod tui_util;
 
 #[tokio::main]
 async fn main() -> x_pixiv_lib::Result<()> {
     match std::env::args().len() {
-        1 => {
+        1 =>
+        {
             #[cfg(feature = "tui")]
-            tui().unwrap();
+            if let Err(err) = tui() {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
         }
         _ =>
         {
             #[cfg(feature = "cli")]
-            cli().await?
+            std::process::exit(cli().await)
         }
     };
 
     Ok(())
 }
 
+/// Runs the command and returns the process exit code.
 #[cfg(feature = "cli")]
-async fn cli() -> x_pixiv_lib::Result<()> {
+async fn cli() -> i32 {
     let cli = Cli::parse();
+    output::set_mode(cli.output);
+    output::finish(run(&cli).await)
+}
+
+#[cfg(feature = "cli")]
+async fn run(cli: &Cli) -> x_pixiv_lib::Result<()> {
+    x_pixiv_lib::client::init(&cli.proxy_settings())?;
+    x_pixiv_lib::host::set_image_hosts(cli.image_host.clone());
+    if let Some(path) = &cli.archive {
+        match Archive::open(path) {
+            Ok(archive) => cli::pipeline::set_archive(Arc::new(Mutex::new(archive))),
+            Err(err) => output::error(None, None, &err, format!("{}: {}", path, err)),
+        }
+    }
     match &cli.command {
         Commands::Rank(args) => rank_downloader(args).await?,
         Commands::Artwork(args) => artwork_download(args).await?,
         Commands::User(args) => user_download(args).await?,
+        Commands::Search(args) => search_download(args).await?,
+        Commands::Sync(args) => sync_download(args).await?,
+        Commands::Get(args) => get_download(args).await?,
+        Commands::Queue(args) => queue_command(args).await?,
+        Commands::Serve(args) => serve(args, cli).await?,
+        Commands::Watch(args) => watch(args).await?,
+        Commands::Dupes(args) => dupes(args).await?,
+        Commands::Gallery(args) => gallery(args).await?,
+        Commands::Pdf(args) => pdf(args).await?,
     }
 
     Ok(())
@@ -51,16 +93,27 @@ async fn cli() -> x_pixiv_lib::Result<()> {
 
 #[cfg(feature = "tui")]
 fn tui() -> Result<(), io::Error> {
+    // a broken config.toml is reported before the terminal is taken over
+    let config_data = ConfigData::load()?;
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // pasted urls arrive as one event instead of key presses that trigger shortcuts
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
-    let mut app_state = AppState::new(vec![
-        ListItem::new("Rank Downloader"),
-        ListItem::new("Artworks Downloader"),
-        ListItem::new("User Downloader"),
-    ]);
+    let mut app_state = AppState::new(
+        vec![
+            ListItem::new("Rank Downloader"),
+            ListItem::new("Artworks Downloader"),
+            ListItem::new("User Downloader"),
+        ],
+        config_data,
+    );
 
     app_state.init();
 
@@ -96,7 +149,8 @@ fn tui() -> Result<(), io::Error> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -1,6 +1,6 @@
 use crossterm::event::Event;
+use std::io::Stdout;
 use std::sync::{Arc, Mutex};
-use std::{collections::HashMap, io::Stdout};
 use tui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
@@ -8,17 +8,18 @@ use tui::{
     widgets::{Block, BorderType, Borders, Paragraph},
     Frame,
 };
-use uuid::Uuid;
 
 use super::compose::Compose;
 use super::data::ConfigData;
-use super::util::download;
-use crate::tui_util::data::DownloadInfo;
+use super::util::Downloader;
 use crossterm::event::KeyCode;
+use x_pixiv_lib::{control::Control, target::artwork_id};
 
 pub struct ArtworkDownloaderState {
     input: String,
     error: Arc<Mutex<bool>>,
+    // the last started download
+    control: Option<Control>,
 }
 
 impl ArtworkDownloaderState {
@@ -26,6 +27,7 @@ impl ArtworkDownloaderState {
         Box::new(Self {
             input: String::new(),
             error: Arc::new(Mutex::new(false)),
+            control: None,
         })
     }
 }
@@ -51,7 +53,7 @@ impl Compose for ArtworkDownloaderState {
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
                 .border_style(focus_style)
-                .title("input artwork id (Enter download)"),
+                .title("input artwork id or paste url (Enter download, p pause, c cancel)"),
         );
 
         f.set_cursor(check[0].x + self.input.len() as u16 + 1, check[0].y + 1);
@@ -61,11 +63,25 @@ impl Compose for ArtworkDownloaderState {
     fn update(
         &mut self,
         event: &crossterm::event::Event,
-        download_queue: Arc<Mutex<HashMap<Uuid, DownloadInfo>>>,
+        downloader: Downloader,
         config: ConfigData,
     ) {
+        if let Event::Paste(text) = event {
+            *self.error.lock().unwrap() = false;
+            self.input = text.trim().to_string();
+        }
         if let Event::Key(code) = event {
             match code.code {
+                KeyCode::Char('p') => {
+                    if let Some(control) = &self.control {
+                        control.toggle_pause();
+                    }
+                }
+                KeyCode::Char('c') => {
+                    if let Some(control) = &self.control {
+                        control.cancel();
+                    }
+                }
                 KeyCode::Char(c) => {
                     *self.error.lock().unwrap() = false;
                     if c.is_ascii_digit() {
@@ -76,12 +92,15 @@ impl Compose for ArtworkDownloaderState {
                     self.input.pop();
                 }
                 KeyCode::Enter => {
-                    let Ok(id) = self.input.parse::<usize>() else {
+                    let Ok(id) = artwork_id(&self.input) else {
+                        *self.error.lock().unwrap() = true;
                         return;
                     };
                     let clone_error = self.error.clone();
+                    let control = downloader.job_control();
+                    self.control = Some(control.clone());
                     tokio::spawn(async move {
-                        if (download(id, download_queue, config).await).is_err() {
+                        if !downloader.download(id, control, config).await {
                             *clone_error.lock().unwrap() = true;
                         };
                     });
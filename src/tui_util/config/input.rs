@@ -13,23 +13,34 @@ use crate::tui_util::data::ConfigData;
 
 use super::ConfigItem;
 
-pub struct OutputConfig {
+/// Text input bound to a single `ConfigData` field.
+pub struct InputConfig {
+    name: &'static str,
     input: String,
     edit: bool,
+    get: fn(&ConfigData) -> String,
+    set: fn(&mut ConfigData, String),
 }
 
-impl OutputConfig {
-    pub fn new() -> Box<Self> {
+impl InputConfig {
+    pub fn new(
+        name: &'static str,
+        get: fn(&ConfigData) -> String,
+        set: fn(&mut ConfigData, String),
+    ) -> Box<Self> {
         Box::new(Self {
+            name,
             input: String::new(),
             edit: false,
+            get,
+            set,
         })
     }
 }
 
-impl ConfigItem for OutputConfig {
+impl ConfigItem for InputConfig {
     fn init(&mut self, config_data: &ConfigData) {
-        self.input = config_data.output.clone();
+        self.input = (self.get)(config_data);
     }
 
     fn render(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>, forcu: bool) {
@@ -38,7 +49,7 @@ impl ConfigItem for OutputConfig {
             .direction(Direction::Horizontal)
             .split(area);
         let forcu_style = Style::default().fg(if forcu { Color::White } else { Color::DarkGray });
-        let config_name = Paragraph::new("Output Path").style(forcu_style);
+        let config_name = Paragraph::new(self.name).style(forcu_style);
         let input = Paragraph::new(self.input.clone()).block(
             Block::default()
                 .borders(Borders::ALL)
@@ -66,19 +77,15 @@ impl ConfigItem for OutputConfig {
     fn update(&mut self, config: &mut ConfigData, event: &Event) {
         if let Event::Key(key) = event {
             match key.code {
-                KeyCode::Char(c) => {
-                    if self.edit {
-                        self.input.push(c);
-                    }
+                KeyCode::Char(c) if self.edit => {
+                    self.input.push(c);
                 }
-                KeyCode::Backspace => {
-                    if self.edit {
-                        self.input.pop();
-                    }
+                KeyCode::Backspace if self.edit => {
+                    self.input.pop();
                 }
                 KeyCode::Enter => {
                     if self.edit {
-                        config.output = self.input.clone();
+                        (self.set)(config, self.input.clone());
                         self.edit = false;
                         config.save();
                     } else {
@@ -1,9 +1,16 @@
 mod group;
-mod output;
+mod input;
+mod sidecar;
+mod toggle;
 
-use self::{group::GroupConfig, output::OutputConfig};
+use self::{group::GroupConfig, input::InputConfig, sidecar::SidecarConfig, toggle::ToggleConfig};
 
 use super::data::ConfigData;
+use x_pixiv_lib::{
+    client,
+    filter::TagsMatch,
+    host::{self, ImageHost},
+};
 
 use crossterm::event::{Event, KeyCode};
 use std::{
@@ -14,7 +21,7 @@ use tui::{
     backend::CrosstermBackend,
     layout::{Margin, Rect},
     style::{Color, Style},
-    text::Span,
+    text::{Span, Spans},
     widgets::{Block, BorderType, Borders, Clear},
     Frame,
 };
@@ -23,12 +30,129 @@ pub struct Config {
     pub config_data: ConfigData,
     state: ConfigState,
     config_items: Arc<Mutex<Vec<Box<dyn ConfigItem>>>>,
+    /// why the last change was not taken
+    error: Option<String>,
 }
 
 impl Config {
     pub fn new(config_data: ConfigData) -> Self {
-        let mut config_items: Vec<Box<dyn ConfigItem>> =
-            vec![OutputConfig::new(), GroupConfig::new()];
+        let mut config_items: Vec<Box<dyn ConfigItem>> = vec![
+            InputConfig::new(
+                "Output Path",
+                |config| config.output.clone(),
+                |config, value| config.output = value,
+            ),
+            GroupConfig::new(),
+            SidecarConfig::new(),
+            ToggleConfig::new(
+                "Embed Metadata",
+                |config| config.embed_metadata,
+                |config, value| config.embed_metadata = value,
+            ),
+            ToggleConfig::new(
+                "Resume",
+                |config| config.resume,
+                |config, value| config.resume = value,
+            ),
+            ToggleConfig::new(
+                "Resume Queue",
+                |config| config.resume_queue,
+                |config, value| config.resume_queue = value,
+            ),
+            ToggleConfig::new(
+                "Pack CBZ",
+                |config| config.cbz,
+                |config, value| config.cbz = value,
+            ),
+            ToggleConfig::new(
+                "Write PDF",
+                |config| config.pdf,
+                |config, value| config.pdf = value,
+            ),
+            ToggleConfig::new(
+                "Skip Archived",
+                |config| config.skip_archived,
+                |config, value| config.skip_archived = value,
+            ),
+            InputConfig::new(
+                "Include Tags",
+                |config| config.filter.include_tags.join(","),
+                |config, value| config.filter.include_tags = split_list(&value),
+            ),
+            InputConfig::new(
+                "Exclude Tags",
+                |config| config.filter.exclude_tags.join(","),
+                |config, value| config.filter.exclude_tags = split_list(&value),
+            ),
+            ToggleConfig::new(
+                "Match All Tags",
+                |config| config.filter.tags_match == TagsMatch::All,
+                |config, value| {
+                    config.filter.tags_match = if value {
+                        TagsMatch::All
+                    } else {
+                        TagsMatch::Any
+                    }
+                },
+            ),
+            ToggleConfig::new(
+                "Tag Regex",
+                |config| config.filter.tag_regex,
+                |config, value| config.filter.tag_regex = value,
+            ),
+            ToggleConfig::new(
+                "Exclude AI",
+                |config| config.filter.exclude_ai,
+                |config, value| config.filter.exclude_ai = value,
+            ),
+            InputConfig::new(
+                "Min Bookmarks",
+                |config| config.filter.min_bookmarks.to_string(),
+                |config, value| config.filter.min_bookmarks = value.trim().parse().unwrap_or(0),
+            ),
+            InputConfig::new(
+                "Proxy",
+                |config| config.proxy.api.clone().unwrap_or_default(),
+                |config, value| {
+                    config.proxy.api = Some(value).filter(|value| !value.is_empty());
+                    client::init(&config.proxy).ok();
+                },
+            ),
+            InputConfig::new(
+                "Image Proxy",
+                |config| config.proxy.image.clone().unwrap_or_default(),
+                |config, value| {
+                    config.proxy.image = Some(value).filter(|value| !value.is_empty());
+                    client::init(&config.proxy).ok();
+                },
+            ),
+            InputConfig::new(
+                "No Proxy",
+                |config| config.proxy.no_proxy.join(","),
+                |config, value| {
+                    config.proxy.no_proxy = split_list(&value);
+                    client::init(&config.proxy).ok();
+                },
+            ),
+            InputConfig::new(
+                "Image Hosts",
+                |config| {
+                    config
+                        .image_hosts
+                        .iter()
+                        .map(|image_host| image_host.to_string())
+                        .collect::<Vec<String>>()
+                        .join(",")
+                },
+                |config, value| {
+                    config.image_hosts = split_list(&value)
+                        .iter()
+                        .filter_map(|image_host| image_host.parse::<ImageHost>().ok())
+                        .collect();
+                    host::set_image_hosts(config.image_hosts.clone());
+                },
+            ),
+        ];
 
         config_items.iter_mut().for_each(|item| {
             item.init(&config_data);
@@ -38,6 +162,7 @@ impl Config {
             config_data,
             state: ConfigState::new(),
             config_items: Arc::new(Mutex::new(config_items)),
+            error: None,
         }
     }
 
@@ -62,18 +187,36 @@ impl Config {
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
             .border_style(Style::default().fg(Color::Cyan))
-            .title(Span::styled(
-                "Config",
-                Style::default().fg(Color::LightCyan),
-            ));
+            .title(Spans::from(vec![
+                Span::styled("Config", Style::default().fg(Color::LightCyan)),
+                Span::styled(
+                    self.error
+                        .as_ref()
+                        .map(|error| format!(" {}", error))
+                        .unwrap_or_default(),
+                    Style::default().fg(Color::Red),
+                ),
+            ]));
 
         f.render_widget(Clear, content_rect);
         f.render_widget(block, rect);
 
-        for (i, item) in self.config_items.lock().unwrap().iter().enumerate() {
+        // scroll so the selected item stays visible
+        let visible = (content_rect.height / 3).max(1) as usize;
+        let offset = (self.state.selected() + 1).saturating_sub(visible);
+
+        for (i, item) in self
+            .config_items
+            .lock()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(visible)
+        {
             let item_rect = Rect {
                 x: content_rect.x,
-                y: content_rect.y + i as u16 * 3,
+                y: content_rect.y + (i - offset) as u16 * 3,
                 width: content_rect.width,
                 height: 3,
             };
@@ -86,6 +229,7 @@ impl Config {
         let config_items = self.config_items.clone();
 
         if let Event::Key(key) = event {
+            self.error = None;
             match key.code {
                 KeyCode::Up => self.prev(),
                 KeyCode::Down => self.next(),
@@ -93,7 +237,18 @@ impl Config {
             }
         }
 
-        config_items.lock().unwrap()[self.state.selected()].update(&mut self.config_data, event);
+        let filter = self.config_data.filter.clone();
+        let mut config_items = config_items.lock().unwrap();
+        let item = &mut config_items[self.state.selected()];
+
+        item.update(&mut self.config_data, event);
+        // an invalid tag regex would match nothing, the last valid filter is kept
+        if let Err(err) = self.config_data.filter.validate() {
+            self.config_data.filter = filter;
+            self.config_data.save();
+            item.init(&self.config_data);
+            self.error = Some(err);
+        }
     }
 
     fn next(&mut self) {
@@ -117,14 +272,20 @@ impl Config {
 
 impl Default for Config {
     fn default() -> Self {
-        Config::new(ConfigData {
-            output: "./images".to_string(),
-            group_type: None,
-        })
+        Config::new(ConfigData::default())
     }
 }
 
-trait ConfigItem {
+/// Splits a comma separated config value.
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+trait ConfigItem: Send {
     fn init(&mut self, config_data: &ConfigData);
 
     fn render(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>, forcu: bool);
@@ -0,0 +1,101 @@
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use std::io::Stdout;
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Style},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::tui_util::data::ConfigData;
+use x_pixiv_lib::sidecar::SidecarFormat;
+
+use super::ConfigItem;
+
+pub struct SidecarConfig {
+    sidecar: Option<SidecarFormat>,
+    formats: Vec<Option<SidecarFormat>>,
+    index: usize,
+}
+
+impl SidecarConfig {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {
+            index: 0,
+            sidecar: None,
+            formats: vec![None, Some(SidecarFormat::Json), Some(SidecarFormat::Toml)],
+        })
+    }
+
+    fn next(&mut self) {
+        self.index = if self.index + 1 >= self.formats.len() {
+            0
+        } else {
+            self.index + 1
+        };
+
+        self.sidecar = self.formats[self.index];
+    }
+
+    fn prev(&mut self) {
+        self.index = if self.index == 0 {
+            self.formats.len() - 1
+        } else {
+            self.index - 1
+        };
+
+        self.sidecar = self.formats[self.index];
+    }
+}
+
+impl ConfigItem for SidecarConfig {
+    fn init(&mut self, config_data: &ConfigData) {
+        self.sidecar = config_data.sidecar;
+    }
+
+    fn render(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>, forcu: bool) {
+        let check = Layout::default()
+            .constraints([Constraint::Percentage(20), Constraint::Percentage(80)])
+            .direction(Direction::Horizontal)
+            .split(area);
+        let forcu_style = Style::default().fg(if forcu { Color::White } else { Color::DarkGray });
+        let config_name = Paragraph::new("Sidecar").style(forcu_style);
+        let format_str = if let Some(format) = &self.sidecar {
+            format.to_string()
+        } else {
+            "None".to_string()
+        };
+        let config_value =
+            Paragraph::new(format!("◀ {} ▶", format_str)).alignment(Alignment::Center);
+
+        f.render_widget(
+            config_name,
+            check[0].inner(&Margin {
+                horizontal: 1,
+                vertical: 1,
+            }),
+        );
+        f.render_widget(
+            config_value,
+            check[1].inner(&Margin {
+                horizontal: 5,
+                vertical: 1,
+            }),
+        );
+    }
+
+    fn update(&mut self, config: &mut ConfigData, event: &Event) {
+        if let Event::Key(key) = event {
+            match key.code {
+                KeyCode::Left => self.prev(),
+                KeyCode::Right => self.next(),
+                _ => {}
+            }
+
+            config.sidecar = self.sidecar;
+            config.save();
+        };
+    }
+}
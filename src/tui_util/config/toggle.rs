@@ -0,0 +1,79 @@
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use std::io::Stdout;
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Style},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::tui_util::data::ConfigData;
+
+use super::ConfigItem;
+
+/// On / off setting.
+pub struct ToggleConfig {
+    name: &'static str,
+    value: bool,
+    get: fn(&ConfigData) -> bool,
+    set: fn(&mut ConfigData, bool),
+}
+
+impl ToggleConfig {
+    pub fn new(
+        name: &'static str,
+        get: fn(&ConfigData) -> bool,
+        set: fn(&mut ConfigData, bool),
+    ) -> Box<Self> {
+        Box::new(Self {
+            name,
+            value: false,
+            get,
+            set,
+        })
+    }
+}
+
+impl ConfigItem for ToggleConfig {
+    fn init(&mut self, config_data: &ConfigData) {
+        self.value = (self.get)(config_data);
+    }
+
+    fn render(&self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>, forcu: bool) {
+        let check = Layout::default()
+            .constraints([Constraint::Percentage(20), Constraint::Percentage(80)])
+            .direction(Direction::Horizontal)
+            .split(area);
+        let forcu_style = Style::default().fg(if forcu { Color::White } else { Color::DarkGray });
+        let config_name = Paragraph::new(self.name).style(forcu_style);
+        let config_value = Paragraph::new(format!("◀ {} ▶", if self.value { "On" } else { "Off" }))
+            .alignment(Alignment::Center);
+
+        f.render_widget(
+            config_name,
+            check[0].inner(&Margin {
+                horizontal: 1,
+                vertical: 1,
+            }),
+        );
+        f.render_widget(
+            config_value,
+            check[1].inner(&Margin {
+                horizontal: 5,
+                vertical: 1,
+            }),
+        );
+    }
+
+    fn update(&mut self, config: &mut ConfigData, event: &Event) {
+        if let Event::Key(key) = event {
+            if let KeyCode::Left | KeyCode::Right = key.code {
+                self.value = !self.value;
+                (self.set)(config, self.value);
+                config.save();
+            }
+        };
+    }
+}
@@ -6,14 +6,10 @@ mod rank;
 mod user;
 mod util;
 
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{Read, Stdout};
-use std::sync::{Arc, Mutex};
+use std::io::Stdout;
 
 use crate::tui_util::compose::Compose;
-use crossterm::event::{Event, KeyCode};
-use data::DownloadInfo;
+use crossterm::event::{Event, KeyCode, KeyEvent};
 use rank::RankState;
 use tui::{
     backend::CrosstermBackend,
@@ -22,43 +18,56 @@ use tui::{
     widgets::{Block, BorderType, Borders, Gauge, List, ListItem, ListState},
     Frame,
 };
-use uuid::Uuid;
+use x_pixiv_lib::{
+    client, host,
+    job::{JobManager, ManagerOptions},
+};
 
 use self::artwork::ArtworkDownloaderState;
 use self::config::Config;
 use self::data::ConfigData;
 use self::user::UserDownloaderState;
+use self::util::{track_downloads, DownloadQueue, Downloader};
 
 pub struct AppState<'a> {
     menu: Vec<ListItem<'a>>,
     menu_state: ListState,
     pub contents: Vec<Box<dyn Compose>>,
     pub focus: bool,
-    download_queue: Arc<Mutex<HashMap<Uuid, DownloadInfo>>>,
+    download_queue: DownloadQueue,
+    downloader: Downloader,
+    paused_all: bool,
     config: Config,
     pub config_open: bool,
 }
 
 impl<'a> AppState<'a> {
-    pub fn new(menu: Vec<ListItem<'a>>) -> Self {
+    pub fn new(menu: Vec<ListItem<'a>>, config_data: ConfigData) -> Self {
         let rank_downloader_state = RankState::new(vec![
             "daily", "weekly", "monthly", "rookie", "original", "daily_ai", "male", "female",
         ]);
         let artwork_state = ArtworkDownloaderState::new();
         let user_state = UserDownloaderState::new();
-        let config_data = if let Ok(mut file) = File::open("./config.toml") {
-            let mut content = String::new();
-            file.read_to_string(&mut content).unwrap();
-            toml::from_str::<ConfigData>(&content).unwrap()
-        } else {
-            ConfigData::default().save()
-        };
+        client::init(&config_data.proxy).ok();
+        host::set_image_hosts(config_data.image_hosts.clone());
+        let download_queue = DownloadQueue::default();
+        let (manager, events) = JobManager::new(ManagerOptions {
+            archive: config_data.open_archive().ok().flatten(),
+            ..Default::default()
+        });
+        tokio::spawn(track_downloads(events, download_queue.clone()));
+        let downloader = Downloader::new(manager, config_data.queue_file());
+        if config_data.resume_queue {
+            downloader.resume_queue();
+        }
         Self {
             menu,
             menu_state: ListState::default(),
             focus: true,
             contents: vec![rank_downloader_state, artwork_state, user_state],
-            download_queue: Arc::new(Mutex::new(HashMap::new())),
+            download_queue,
+            downloader,
+            paused_all: false,
             config: Config::new(config_data),
             config_open: false,
         }
@@ -104,6 +113,12 @@ impl<'a> AppState<'a> {
     pub fn update(&mut self, event: &Event) {
         if self.config_open {
             self.config.update(event);
+        } else if let Event::Key(KeyEvent {
+            code: code @ KeyCode::Char('P' | 'C'),
+            ..
+        }) = event
+        {
+            self.control_all(*code);
         } else if self.focus {
             if let Event::Key(key_event) = event {
                 match key_event.code {
@@ -115,12 +130,31 @@ impl<'a> AppState<'a> {
         } else if let Some(content) = self.contents.get_mut(self.menu_state.selected().unwrap()) {
             content.update(
                 event,
-                self.download_queue.clone(),
+                self.downloader.clone(),
                 self.config.config_data.clone(),
             );
         }
     }
 
+    /// Shift+P pauses or resumes every download, Shift+C cancels them.
+    fn control_all(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('P') => {
+                if self.paused_all {
+                    self.downloader.manager.resume_all();
+                } else {
+                    self.downloader.manager.pause_all();
+                }
+                self.paused_all = !self.paused_all;
+            }
+            KeyCode::Char('C') => {
+                self.downloader.manager.cancel_all();
+                self.paused_all = false;
+            }
+            _ => {}
+        }
+    }
+
     pub fn ui(&mut self, f: &mut Frame<CrosstermBackend<Stdout>>) {
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
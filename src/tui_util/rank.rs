@@ -1,12 +1,10 @@
-use super::{data::ConfigData, util::download};
+use super::{data::ConfigData, util::Downloader};
 use crate::cli::parse_agrs_type;
 use crate::tui_util::compose::Compose;
-use crate::tui_util::data::DownloadInfo;
 use crossterm::event::{Event, KeyCode, MouseEventKind};
 use std::{
-    collections::HashMap,
     io::Stdout,
-    sync::{Arc, Mutex, RwLock},
+    sync::{Arc, RwLock},
 };
 use tokio::task::JoinHandle;
 use tui::{
@@ -17,8 +15,10 @@ use tui::{
     widgets::{Block, BorderType, Borders, List, ListItem, ListState, Tabs},
     Frame,
 };
-use uuid::Uuid;
-use x_pixiv_lib::data::Content;
+use x_pixiv_lib::{
+    control::{Control, ControlState},
+    data::Content,
+};
 
 pub struct RankState<'a> {
     tabs_index: usize,
@@ -33,6 +33,7 @@ struct ArtworkInfo {
     content: Content,
     error: bool,
     downloading: bool,
+    control: Option<Control>,
 }
 
 impl ArtworkInfo {
@@ -41,8 +42,15 @@ impl ArtworkInfo {
             content,
             error: false,
             downloading: false,
+            control: None,
         }
     }
+
+    fn is_paused(&self) -> bool {
+        self.control
+            .as_ref()
+            .is_some_and(|control| control.state() == ControlState::Paused)
+    }
 }
 
 impl<'a> RankState<'a> {
@@ -110,6 +118,14 @@ impl<'a> RankState<'a> {
         self.rank_list_state.select(i);
     }
 
+    fn selected_control(&self) -> Option<Control> {
+        let index = self.rank_list_state.selected()?;
+
+        self.rank_list.read().unwrap()[self.tabs_index]
+            .get(index)
+            .and_then(|info| info.control.clone())
+    }
+
     fn get_data(&mut self) {
         let rank_list_clone = self.rank_list.clone();
         let rank_type = parse_agrs_type(self.tabs[self.tabs_index]);
@@ -189,6 +205,8 @@ impl<'a> Compose for RankState<'a> {
                     ))
                     .style(Style::default().bg(if info.error {
                         Color::Red
+                    } else if info.downloading && info.is_paused() {
+                        Color::Yellow
                     } else if info.downloading {
                         Color::LightGreen
                     } else {
@@ -209,7 +227,11 @@ impl<'a> Compose for RankState<'a> {
                     Span::raw("ll "),
                     Span::raw("download | "),
                     Span::styled("R", red_style),
-                    Span::raw("eload list")
+                    Span::raw("eload list | "),
+                    Span::styled("P", red_style),
+                    Span::raw("ause | "),
+                    Span::styled("C", red_style),
+                    Span::raw("ancel (Shift for all)")
                 ])),
         )
         .style(Style::default().add_modifier(Modifier::BOLD))
@@ -226,7 +248,7 @@ impl<'a> Compose for RankState<'a> {
     fn update(
         &mut self,
         event: &Event,
-        download_queue: Arc<Mutex<HashMap<Uuid, DownloadInfo>>>,
+        downloader: Downloader,
         config: ConfigData,
     ) {
         match event {
@@ -247,12 +269,16 @@ impl<'a> Compose for RankState<'a> {
                     let index = self.rank_list_state.selected().unwrap();
                     let rank_list = self.rank_list.clone();
                     let tab_index = self.tabs_index;
-                    let id = rank_list.read().unwrap()[tab_index][index].content.illust_id;
+                    let id = rank_list.read().unwrap()[tab_index][index]
+                        .content
+                        .illust_id;
+                    let control = downloader.job_control();
 
                     rank_list.write().unwrap()[tab_index][index].downloading = true;
+                    rank_list.write().unwrap()[tab_index][index].control = Some(control.clone());
 
                     tokio::spawn(async move {
-                        if (download(id, download_queue, config).await).is_err() {
+                        if !downloader.download(id, control, config).await {
                             rank_list.write().unwrap()[tab_index][index].error = true;
                         };
                         rank_list.write().unwrap()[tab_index][index].downloading = false;
@@ -264,13 +290,20 @@ impl<'a> Compose for RankState<'a> {
                     let rank_list = self.rank_list.clone();
                     let tab_index = self.tabs_index;
                     let clone_len = self.rank_list.read().unwrap()[tab_index].len();
+                    // stops the loop on cancel all
+                    let batch = downloader.job_control();
 
                     tokio::spawn(async move {
                         for i in 0..clone_len {
+                            if !batch.proceed().await {
+                                break;
+                            }
+                            let control = downloader.job_control();
                             rank_list.write().unwrap()[tab_index][i].downloading = true;
+                            rank_list.write().unwrap()[tab_index][i].control =
+                                Some(control.clone());
                             let id = rank_list.read().unwrap()[tab_index][i].content.illust_id;
-                            if (download(id, download_queue.clone(), config.clone()).await).is_err()
-                            {
+                            if !downloader.download(id, control, config.clone()).await {
                                 rank_list.write().unwrap()[tab_index][i].error = true;
                             };
                             rank_list.write().unwrap()[tab_index][i].downloading = false;
@@ -280,6 +313,16 @@ impl<'a> Compose for RankState<'a> {
                 KeyCode::Char('r') => {
                     self.get_data();
                 }
+                KeyCode::Char('p') => {
+                    if let Some(control) = self.selected_control() {
+                        control.toggle_pause();
+                    }
+                }
+                KeyCode::Char('c') => {
+                    if let Some(control) = self.selected_control() {
+                        control.cancel();
+                    }
+                }
                 _ => {}
             },
             Event::Mouse(mouse_event) => match mouse_event.kind {
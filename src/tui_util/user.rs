@@ -1,6 +1,6 @@
 use crossterm::event::Event;
+use std::io::Stdout;
 use std::sync::{Arc, Mutex, RwLock};
-use std::{collections::HashMap, io::Stdout};
 use tui::widgets::{List, ListItem, ListState};
 use tui::{
     backend::CrosstermBackend,
@@ -10,30 +10,42 @@ use tui::{
     widgets::{Block, BorderType, Borders, Paragraph},
     Frame,
 };
-use uuid::Uuid;
-use x_pixiv_lib::user::User;
+use x_pixiv_lib::{job::JobKind, target::user_id, user::User};
 
 use super::compose::Compose;
 use super::data::ConfigData;
-use super::util::download;
-use crate::tui_util::data::DownloadInfo;
+use super::util::Downloader;
 use crossterm::event::KeyCode;
+use x_pixiv_lib::control::{Control, ControlState};
 
 pub struct UserDownloaderState {
     input: String,
     artworks: Arc<RwLock<Vec<ArtworkInfo>>>,
     artowrks_state: ListState,
     error: Arc<Mutex<bool>>,
+    /// user the artworks are listed for
+    user: Arc<Mutex<Option<usize>>>,
 }
 
 struct ArtworkInfo {
     id: usize,
     error: bool,
+    control: Option<Control>,
 }
 
 impl ArtworkInfo {
     fn new(id: usize) -> Self {
-        Self { id, error: false }
+        Self {
+            id,
+            error: false,
+            control: None,
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.control
+            .as_ref()
+            .is_some_and(|control| control.state() == ControlState::Paused)
     }
 }
 
@@ -44,16 +56,19 @@ impl UserDownloaderState {
             artworks: Arc::new(RwLock::new(vec![])),
             artowrks_state: ListState::default(),
             error: Arc::new(Mutex::new(false)),
+            user: Arc::new(Mutex::new(None)),
         })
     }
 
     fn get_user_all_artwork(&mut self) {
-        let Ok(id) = self.input.parse::<usize>() else {
+        let Ok(id) = user_id(&self.input) else {
+            *self.error.lock().unwrap() = true;
             return;
         };
         let user = User::new(id);
         let clone_user_artworks = self.artworks.clone();
         let clone_error = self.error.clone();
+        let clone_user = self.user.clone();
 
         tokio::spawn(async move {
             let Ok(ids) = user.get_artworks().await else {
@@ -64,9 +79,20 @@ impl UserDownloaderState {
 
             write.clear();
             write.append(&mut ids.iter().map(|id| ArtworkInfo::new(*id)).collect());
+            *clone_user.lock().unwrap() = Some(id);
         });
     }
 
+    fn selected_control(&self) -> Option<Control> {
+        let index = self.artowrks_state.selected()?;
+
+        self.artworks
+            .read()
+            .unwrap()
+            .get(index)
+            .and_then(|info| info.control.clone())
+    }
+
     fn next(&mut self) {
         let i = match self.artowrks_state.selected() {
             Some(i) => {
@@ -77,7 +103,7 @@ impl UserDownloaderState {
                 }
             }
             None => {
-                if self.artworks.read().unwrap().len() == 0 {
+                if self.artworks.read().unwrap().is_empty() {
                     None
                 } else {
                     Some(0)
@@ -98,7 +124,7 @@ impl UserDownloaderState {
                 }
             }
             None => {
-                if self.artworks.read().unwrap().len() == 0 {
+                if self.artworks.read().unwrap().is_empty() {
                     None
                 } else {
                     Some(self.artworks.read().unwrap().len() - 1)
@@ -131,7 +157,7 @@ impl Compose for UserDownloaderState {
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
                 .border_style(focus_style)
-                .title("input user id (Enter confirm)"),
+                .title("input user id or paste url (Enter confirm)"),
         );
 
         let list = List::new(
@@ -143,6 +169,8 @@ impl Compose for UserDownloaderState {
                     ListItem::new(format!("https://www.pixiv.net/artworks/{}", item.id)).style(
                         if item.error {
                             Style::default().bg(Color::Red)
+                        } else if item.is_paused() {
+                            Style::default().bg(Color::Yellow)
                         } else {
                             Style::default()
                         },
@@ -160,7 +188,11 @@ impl Compose for UserDownloaderState {
                     Span::raw(" download selected | "),
                     Span::styled("A", Style::default().fg(Color::Red)),
                     Span::raw("ll "),
-                    Span::raw("download"),
+                    Span::raw("download | "),
+                    Span::styled("P", Style::default().fg(Color::Red)),
+                    Span::raw("ause | "),
+                    Span::styled("C", Style::default().fg(Color::Red)),
+                    Span::raw("ancel (Shift for all)"),
                 ])),
         )
         .highlight_style(Style::default().bg(Color::Gray));
@@ -174,12 +206,11 @@ impl Compose for UserDownloaderState {
         f.render_stateful_widget(list, check[1], &mut self.artowrks_state);
     }
 
-    fn update(
-        &mut self,
-        event: &Event,
-        download_queue: Arc<Mutex<HashMap<Uuid, DownloadInfo>>>,
-        config: ConfigData,
-    ) {
+    fn update(&mut self, event: &Event, downloader: Downloader, config: ConfigData) {
+        if let Event::Paste(text) = event {
+            *self.error.lock().unwrap() = false;
+            self.input = text.trim().to_string();
+        }
         if let Event::Key(code) = event {
             match code.code {
                 KeyCode::Char(c) => {
@@ -187,21 +218,30 @@ impl Compose for UserDownloaderState {
                     if c.is_ascii_digit() && self.artowrks_state.selected().is_none() {
                         self.input.push(c);
                     } else if c == 'a' {
-                        let artworks = self.artworks.clone();
-                        let len = self.artworks.read().unwrap().len();
+                        let Some(id) = *self.user.lock().unwrap() else {
+                            return;
+                        };
+                        // one job for the user, pausing or cancelling any artwork acts on it
+                        let control = downloader.job_control();
+                        for info in self.artworks.write().unwrap().iter_mut() {
+                            info.control = Some(control.clone());
+                        }
+                        let error = self.error.clone();
 
                         tokio::spawn(async move {
-                            for i in 0..len {
-                                let id = artworks.read().unwrap()[i].id;
-                                if (download(id, download_queue.clone(), config.clone()).await)
-                                    .is_err()
-                                {
-                                    artworks.write().unwrap()[i].error = true;
-                                } else {
-                                    artworks.write().unwrap()[i].error = false;
-                                };
+                            let kind = JobKind::User(id);
+                            if !downloader.download_job(kind, control, config).await {
+                                *error.lock().unwrap() = true;
                             }
                         });
+                    } else if c == 'p' || c == 'c' {
+                        if let Some(control) = self.selected_control() {
+                            if c == 'p' {
+                                control.toggle_pause();
+                            } else {
+                                control.cancel();
+                            }
+                        }
                     }
                 }
                 KeyCode::Backspace => {
@@ -211,8 +251,10 @@ impl Compose for UserDownloaderState {
                     if let Some(i) = self.artowrks_state.selected() {
                         let id = self.artworks.read().unwrap()[i].id;
                         let artworks = self.artworks.clone();
+                        let control = downloader.job_control();
+                        self.artworks.write().unwrap()[i].control = Some(control.clone());
                         tokio::spawn(async move {
-                            if (download(id, download_queue, config).await).is_err() {
+                            if !downloader.download(id, control, config).await {
                                 artworks.write().unwrap()[i].error = true;
                             };
                         });
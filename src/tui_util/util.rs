@@ -1,58 +1,141 @@
 use std::{
     collections::HashMap,
-    path::PathBuf,
     sync::{Arc, Mutex},
 };
-use uuid::Uuid;
-use x_pixiv_lib::{artworks::get_artworks_data, downloader::downloader};
-
-use super::data::{ConfigData, DownloadInfo, GroupType};
-
-pub async fn download(
-    download_id: usize,
-    download_queue: Arc<Mutex<HashMap<Uuid, DownloadInfo>>>,
-    config: ConfigData,
-) -> x_pixiv_lib::Result<()> {
-    let data = get_artworks_data(download_id).await?;
-    let mut queue = HashMap::new();
-
-    for (index, url) in data.images.iter().enumerate() {
-        let update_download_progress = download_queue.clone();
-        let file_name = format!("{}-{}.{}", data.title, index, &url[url.len() - 3..]);
-        let mut path = PathBuf::from(config.output.clone());
-        let info = DownloadInfo::new(data.title.clone());
-        let id = Uuid::new_v4();
-
-        if let Some(group) = &config.group_type {
-            let group = match group {
-                GroupType::Artwork => format!("{}-{}/", data.title, download_id),
-                GroupType::Author => format!("{}/", data.user_name),
-            };
-
-            path.push(group);
-        }
+use tokio::sync::mpsc::UnboundedReceiver;
+use x_pixiv_lib::{
+    control::Control,
+    job::{Job, JobEvent, JobId, JobKind, JobManager},
+    queue::QueueFile,
+};
+
+use super::data::{ConfigData, DownloadInfo};
+
+/// Running file downloads by job, artwork and page.
+pub type DownloadQueue = Arc<Mutex<HashMap<(JobId, usize, usize), DownloadInfo>>>;
+
+/// Runs downloads on the job manager and keeps them in the queue file until they finish,
+/// so downloads that were running when the TUI quit can be resumed.
+#[derive(Clone)]
+pub struct Downloader {
+    pub manager: JobManager,
+    queue: QueueFile,
+}
+
+impl Downloader {
+    pub fn new(manager: JobManager, queue: QueueFile) -> Self {
+        Self { manager, queue }
+    }
+
+    pub fn job_control(&self) -> Control {
+        self.manager.job_control()
+    }
+
+    /// Downloads one artwork, returns false when a page or the metadata failed.
+    pub async fn download(&self, download_id: usize, control: Control, config: ConfigData) -> bool {
+        self.download_job(JobKind::Artwork(download_id), control, config)
+            .await
+    }
+
+    /// Runs a job with the settings of the config, returns false when anything failed.
+    pub async fn download_job(&self, kind: JobKind, control: Control, config: ConfigData) -> bool {
+        let job = Job::new(kind, config.job_options());
+        // the TUI has nowhere to show a broken queue file, the download runs unqueued
+        let entry = self.queue.update(|queue| queue.push(job.clone())).ok();
 
-        download_queue.lock().unwrap().insert(id, info);
-
-        let task = tokio::spawn(downloader(
-            path.join(file_name),
-            url.clone(),
-            move |now_size, total_size| {
-                let mut write_update = update_download_progress.lock().unwrap();
-                let mut info = write_update[&id].clone();
-                info.progress = ((now_size as f64 / total_size as f64) * 100.0) as u64;
-                write_update.insert(id, info);
-            },
-            |_| {},
-        ));
-
-        queue.insert(id, task);
+        self.run(entry, job, control).await
     }
 
-    for (id, task) in queue {
-        task.await.unwrap()?;
-        download_queue.lock().unwrap().remove(&id);
+    /// Starts every job left in the queue file.
+    pub fn resume_queue(&self) {
+        let entries = self.queue.update(|queue| queue.entries.clone());
+
+        for entry in entries.unwrap_or_default() {
+            let downloader = self.clone();
+            let control = self.job_control();
+
+            tokio::spawn(async move { downloader.run(Some(entry.id), entry.job, control).await });
+        }
     }
 
-    Ok(())
+    async fn run(&self, entry: Option<usize>, job: Job, control: Control) -> bool {
+        let result = self.manager.run_with(job, control.clone()).await;
+
+        if let Some(entry) = entry {
+            // a download cancelled in the TUI is not wanted anymore, an interrupted one is
+            if control.is_cancelled() {
+                self.queue.update(|queue| queue.remove(entry)).ok();
+            } else {
+                self.queue.finish(entry, &result).ok();
+            }
+        }
+
+        matches!(result, Ok(summary) if summary.errors == 0)
+    }
+}
+
+/// Keeps the download queue in sync with the job events.
+pub async fn track_downloads(
+    mut events: UnboundedReceiver<JobEvent>,
+    download_queue: DownloadQueue,
+) {
+    let mut titles = HashMap::new();
+
+    while let Some(event) = events.recv().await {
+        let mut queue = download_queue.lock().unwrap();
+
+        match event {
+            JobEvent::ArtworkResolved {
+                job,
+                illust_id,
+                title,
+                ..
+            } => {
+                titles.insert((job, illust_id), title);
+            }
+            JobEvent::FileStarted {
+                job,
+                illust_id,
+                page,
+                ..
+            } => {
+                let title = titles.get(&(job, illust_id)).cloned().unwrap_or_default();
+                queue.insert((job, illust_id, page), DownloadInfo::new(title));
+            }
+            JobEvent::Progress {
+                job,
+                illust_id,
+                page,
+                bytes,
+                total,
+            } => {
+                if let Some(info) = queue.get_mut(&(job, illust_id, page)) {
+                    info.progress = ((bytes as f64 / total as f64) * 100.0) as u64;
+                }
+            }
+            JobEvent::FileFinished {
+                job,
+                illust_id,
+                page,
+                ..
+            }
+            | JobEvent::Error {
+                job,
+                illust_id: Some(illust_id),
+                page: Some(page),
+                ..
+            }
+            | JobEvent::Cancelled {
+                job,
+                illust_id,
+                page: Some(page),
+            } => {
+                queue.remove(&(job, illust_id, page));
+            }
+            JobEvent::ArtworkFinished { job, illust_id, .. } => {
+                titles.remove(&(job, illust_id));
+            }
+            _ => {}
+        }
+    }
 }